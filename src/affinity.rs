@@ -0,0 +1,162 @@
+use std::{collections::HashSet, fs, io};
+
+/// Largest CPU index that fits in a `cpu_set_t`.
+pub const MAX_CPU: usize = 1023;
+
+/// How worker threads are placed on CPUs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PinPolicy {
+    /// Leave placement to the scheduler.
+    None,
+    /// Pin workers to these CPUs, in order, wrapping around if there are more workers.
+    Cpus(Vec<usize>),
+    /// Pin one worker to the first logical CPU of each physical core, skipping SMT siblings.
+    PhysicalCores,
+}
+
+impl PinPolicy {
+    /// Parses `none`, `physical`, or a CPU list such as `0-3,8,10`.
+    pub fn parse(text: &str) -> Result<PinPolicy, String> {
+        match text.trim() {
+            "none" => return Ok(PinPolicy::None),
+            "physical" => return Ok(PinPolicy::PhysicalCores),
+            _ => {}
+        }
+        let cpus = parse_cpu_list(text)?;
+        if cpus.is_empty() {
+            return Err(format!("empty CPU list \"{}\"", text));
+        }
+        Ok(PinPolicy::Cpus(cpus))
+    }
+
+    /// Resolves the policy to the CPUs workers should be pinned to, or `None` for no pinning.
+    pub fn cpus(&self) -> io::Result<Option<Vec<usize>>> {
+        match self {
+            PinPolicy::None => Ok(None),
+            PinPolicy::Cpus(cpus) => Ok(Some(cpus.clone())),
+            PinPolicy::PhysicalCores => physical_cores().map(Some),
+        }
+    }
+}
+
+/// Parses a Linux-style CPU list (`0-3,8,10-11`), keeping the given order and dropping duplicates.
+pub fn parse_cpu_list(text: &str) -> Result<Vec<usize>, String> {
+    let invalid = || format!("invalid CPU list \"{}\"", text);
+    let mut cpus = Vec::new();
+    let mut seen = HashSet::new();
+    for part in text.trim().split(',').filter(|part| !part.trim().is_empty()) {
+        let (first, last) = match part.split_once('-') {
+            Some((first, last)) => (first.trim(), last.trim()),
+            None => (part.trim(), part.trim()),
+        };
+        let first: usize = first.parse().map_err(|_| invalid())?;
+        let last: usize = last.parse().map_err(|_| invalid())?;
+        if first > last {
+            return Err(invalid());
+        }
+        if last > MAX_CPU {
+            return Err(format!("CPU {} is out of range (max {})", last, MAX_CPU));
+        }
+        for cpu in first..=last {
+            if seen.insert(cpu) {
+                cpus.push(cpu);
+            }
+        }
+    }
+    Ok(cpus)
+}
+
+/// Returns the first logical CPU of every online physical core, in CPU order.
+pub fn physical_cores() -> io::Result<Vec<usize>> {
+    let online = fs::read_to_string("/sys/devices/system/cpu/online")?;
+    let online = parse_cpu_list(&online).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    let mut cores = HashSet::new();
+    let mut cpus = Vec::new();
+    for cpu in online {
+        let topology = format!("/sys/devices/system/cpu/cpu{}/topology", cpu);
+        let package = fs::read_to_string(format!("{}/physical_package_id", topology))?;
+        let core = fs::read_to_string(format!("{}/core_id", topology))?;
+        if cores.insert((package.trim().to_string(), core.trim().to_string())) {
+            cpus.push(cpu);
+        }
+    }
+    if cpus.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "no physical cores found"));
+    }
+    Ok(cpus)
+}
+
+/// Pins the calling thread to a single CPU.
+#[cfg(target_os = "linux")]
+pub fn pin_current_thread(cpu: usize) -> io::Result<()> {
+    if cpu > MAX_CPU {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("CPU {} is out of range (max {})", cpu, MAX_CPU),
+        ));
+    }
+    // SAFETY: `set` is a plain bitmask owned by this frame, `cpu` was checked against
+    // `MAX_CPU` above, and pid 0 refers to the calling thread.
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_ZERO(&mut set);
+        libc::CPU_SET(cpu, &mut set);
+        if libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+/// Pins the calling thread to a single CPU.
+#[cfg(not(target_os = "linux"))]
+pub fn pin_current_thread(_cpu: usize) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "CPU pinning is only supported on Linux",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_cpu_lists() {
+        let cases = [
+            ("0-3,8", Some(vec![0, 1, 2, 3, 8])),
+            ("3,1,3", Some(vec![3, 1])),
+            ("2-3,0-2", Some(vec![2, 3, 0, 1])),
+            (" 5 - 6 , 1 ", Some(vec![5, 6, 1])),
+            ("0-1,,2", Some(vec![0, 1, 2])),
+            ("1023", Some(vec![1023])),
+            ("0-3\n", Some(vec![0, 1, 2, 3])),
+            ("", Some(vec![])),
+            ("4-1", None),
+            ("1024", None),
+            ("1020-1024", None),
+            ("-1", None),
+            ("a", None),
+            ("1-2-3", None),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(parse_cpu_list(text).ok(), expected, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn parses_pin_policies() {
+        let cases = [
+            ("none", Some(PinPolicy::None)),
+            (" physical ", Some(PinPolicy::PhysicalCores)),
+            ("0,2", Some(PinPolicy::Cpus(vec![0, 2]))),
+            ("", None),
+            (",", None),
+            ("all", None),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(PinPolicy::parse(text).ok(), expected, "{:?}", text);
+        }
+    }
+}
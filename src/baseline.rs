@@ -0,0 +1,131 @@
+use std::fs;
+
+use crate::json::{self, Value};
+
+/// A throughput metric compared between a baseline report and the current run.
+pub struct Comparison {
+    pub metric: &'static str,
+    pub baseline: f64,
+    pub current: f64,
+    pub regressed: bool,
+}
+
+impl Comparison {
+    /// Relative change from the baseline, e.g. `-0.07` for 7% slower.
+    pub fn change(&self) -> f64 {
+        self.current / self.baseline - 1.0
+    }
+}
+
+/// Metrics compared against the baseline, as paths into the final report.
+const METRICS: &[(&str, &[&str])] = &[
+    ("average rate", &["results", "average_rate"]),
+    ("median interval rate", &["results", "interval_rate", "median"]),
+];
+
+/// Loads a final report written by a previous run with `--report`.
+pub fn load(path: &str) -> Result<Value, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let report = json::parse(&text).map_err(|e| format!("{}: {}", path, e))?;
+    if report.pointer(&["results", "average_rate"]).is_none() {
+        return Err(format!("{}: not a string_repeater report", path));
+    }
+    Ok(report)
+}
+
+/// Compares the current report against the baseline. A metric regresses when it
+/// falls more than `tolerance` (a fraction) below the baseline. Metrics missing
+/// from either report, such as the median of a run without samples, are skipped.
+pub fn compare(baseline: &Value, current: &Value, tolerance: f64) -> Vec<Comparison> {
+    METRICS
+        .iter()
+        .filter_map(|(metric, path)| {
+            let baseline = baseline.pointer(path)?.as_f64()?;
+            let current = current.pointer(path)?.as_f64()?;
+            if baseline <= 0.0 {
+                return None;
+            }
+            Some(Comparison {
+                metric,
+                baseline,
+                current,
+                regressed: current < baseline * (1.0 - tolerance),
+            })
+        })
+        .collect()
+}
+
+/// Describes configuration differences that make the comparison questionable.
+pub fn mismatches(baseline: &Value, current: &Value) -> Vec<String> {
+    ["mode", "threads", "input_bytes", "counter"]
+        .iter()
+        .filter_map(|key| {
+            let path = ["config", key];
+            let (baseline, current) = (baseline.pointer(&path)?, current.pointer(&path)?);
+            (baseline != current).then(|| format!("{} differs (baseline {}, current {})", key, baseline, current))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(average_rate: Value, median: Value) -> Value {
+        Value::object([(
+            "results",
+            Value::object([
+                ("average_rate", average_rate),
+                ("interval_rate", Value::object([("median", median)])),
+            ]),
+        )])
+    }
+
+    fn rates(average_rate: f64, median: f64) -> Value {
+        report(Value::from(average_rate), Value::from(median))
+    }
+
+    #[test]
+    fn regresses_only_below_the_tolerance() {
+        let baseline = rates(200.0, 100.0);
+        let cases = [
+            (rates(150.0, 75.0), 0.25, [false, false]),
+            (rates(149.99, 75.0), 0.25, [true, false]),
+            (rates(150.0, 74.99), 0.25, [false, true]),
+            (rates(400.0, 50.0), 0.5, [false, false]),
+            (rates(199.0, 100.0), 0.0, [true, false]),
+            (rates(200.0, 100.0), 0.0, [false, false]),
+        ];
+        for (current, tolerance, expected) in cases {
+            let regressed: Vec<bool> = compare(&baseline, &current, tolerance)
+                .iter()
+                .map(|comparison| comparison.regressed)
+                .collect();
+            assert_eq!(regressed, expected, "{} at {}", current, tolerance);
+        }
+    }
+
+    #[test]
+    fn reports_the_relative_change() {
+        let comparisons = compare(&rates(200.0, 100.0), &rates(150.0, 110.0), 0.05);
+        let changes: Vec<(&str, f64)> = comparisons.iter().map(|c| (c.metric, c.change())).collect();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].0, "average rate");
+        assert!((changes[0].1 + 0.25).abs() < 1e-12);
+        assert_eq!(changes[1].0, "median interval rate");
+        assert!((changes[1].1 - 0.1).abs() < 1e-12);
+    }
+
+    #[test]
+    fn skips_metrics_that_cannot_be_compared() {
+        let no_median = report(Value::from(100.0), Value::Null);
+        let metrics = |baseline: &Value, current: &Value| -> Vec<&'static str> {
+            compare(baseline, current, 0.05).iter().map(|c| c.metric).collect()
+        };
+        assert_eq!(metrics(&no_median, &rates(10.0, 10.0)), ["average rate"]);
+        assert_eq!(metrics(&rates(10.0, 10.0), &no_median), ["average rate"]);
+        assert_eq!(metrics(&rates(0.0, 100.0), &rates(10.0, 10.0)), ["median interval rate"]);
+        assert_eq!(metrics(&rates(-1.0, 0.0), &rates(10.0, 10.0)), Vec::<&str>::new());
+        assert!(metrics(&Value::object::<&str>([]), &rates(10.0, 10.0)).is_empty());
+    }
+}
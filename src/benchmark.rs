@@ -0,0 +1,197 @@
+use std::{
+    net::TcpListener,
+    sync::{atomic::AtomicBool, Arc},
+    thread,
+    time::Duration,
+};
+
+use crate::corpus::{Corpus, Selection};
+use crate::counter::CounterMode;
+use crate::generate;
+use crate::runner::{self, RunOutputs, RunResult, RunSpec};
+use crate::sink::{self, ConsoleSink, Formatter, Observer, RunInfo};
+use crate::workload::{self, WorkloadInfo, DEFAULT_WORKLOAD};
+
+/// Default interval between samples.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Builds and runs one measurement.
+///
+/// Without a duration or iteration limit the run only stops when the flag
+/// passed to [`Benchmark::run_until`] is set, so set at least one limit when
+/// using [`Benchmark::run`].
+pub struct Benchmark {
+    spec: RunSpec,
+    /// Worker threads; `None` means one per pinned CPU, or per available CPU.
+    threads: Option<usize>,
+    run_id: Option<String>,
+    observers: Vec<Box<dyn Observer>>,
+    metrics_listener: Option<TcpListener>,
+    quiet: bool,
+}
+
+impl Benchmark {
+    /// A benchmark repeating one string.
+    pub fn new(input: impl Into<String>) -> Benchmark {
+        Benchmark::with_corpus(Corpus::single(input.into()))
+    }
+
+    /// A benchmark picking its inputs from a corpus.
+    pub fn with_corpus(corpus: Corpus) -> Benchmark {
+        Benchmark {
+            spec: RunSpec {
+                corpus: Arc::new(corpus),
+                selection: Selection::RoundRobin,
+                seed: generate::DEFAULT_SEED,
+                workload: workload::find(DEFAULT_WORKLOAD).expect("default workload is registered"),
+                threads: 0,
+                pinned_cpus: None,
+                counter_mode: CounterMode::Sharded,
+                batch_size: 1,
+                duration: None,
+                iterations: None,
+                warmup: None,
+                interval: DEFAULT_INTERVAL,
+            },
+            threads: None,
+            run_id: None,
+            observers: Vec::new(),
+            metrics_listener: None,
+            quiet: true,
+        }
+    }
+
+    pub fn workload(mut self, workload: &'static WorkloadInfo) -> Self {
+        self.spec.workload = workload;
+        self
+    }
+
+    /// Number of worker threads; must be at least 1. Defaults to one per pinned
+    /// CPU, or per available CPU.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    /// Pins worker `i` to the `i`-th CPU of the list, wrapping around. The list must
+    /// not be empty or name a CPU above [`crate::affinity::MAX_CPU`].
+    pub fn pin(mut self, cpus: Vec<usize>) -> Self {
+        self.spec.pinned_cpus = Some(cpus);
+        self
+    }
+
+    pub fn counter_mode(mut self, counter_mode: CounterMode) -> Self {
+        self.spec.counter_mode = counter_mode;
+        self
+    }
+
+    /// Iterations a worker counts locally before publishing them; must be at least 1.
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.spec.batch_size = batch_size;
+        self
+    }
+
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.spec.duration = Some(duration);
+        self
+    }
+
+    pub fn iterations(mut self, iterations: usize) -> Self {
+        self.spec.iterations = Some(iterations);
+        self
+    }
+
+    /// Runs uncounted for this long before measuring.
+    pub fn warmup(mut self, warmup: Duration) -> Self {
+        self.spec.warmup = Some(warmup).filter(|warmup| !warmup.is_zero());
+        self
+    }
+
+    /// Time between samples passed to the observers; must be greater than zero.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.spec.interval = interval;
+        self
+    }
+
+    /// How workers pick corpus entries, and the seed of weighted selection.
+    pub fn selection(mut self, selection: Selection, seed: u64) -> Self {
+        self.spec.selection = selection;
+        self.spec.seed = seed;
+        self
+    }
+
+    /// Identifies the run in structured output; generated if not set.
+    pub fn run_id(mut self, run_id: impl Into<String>) -> Self {
+        self.run_id = Some(run_id.into());
+        self
+    }
+
+    /// Adds an observer that receives every sample and the results; call
+    /// repeatedly to attach several.
+    pub fn observer(mut self, observer: impl Observer + 'static) -> Self {
+        self.observers.push(Box::new(observer));
+        self
+    }
+
+    /// Adds already boxed observers.
+    pub fn observers(mut self, observers: impl IntoIterator<Item = Box<dyn Observer>>) -> Self {
+        self.observers.extend(observers);
+        self
+    }
+
+    /// Prints every sample to stdout with this formatter.
+    pub fn console(self, formatter: Formatter) -> Self {
+        self.observer(ConsoleSink::new(formatter))
+    }
+
+    /// Serves Prometheus metrics on this listener while the run is measured.
+    pub fn metrics(mut self, listener: TcpListener) -> Self {
+        self.metrics_listener = Some(listener);
+        self
+    }
+
+    /// Whether to suppress progress messages and warnings on the console; benchmarks
+    /// are quiet by default. Warnings are also returned in [`RunResult::warnings`].
+    pub fn quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
+    }
+
+    /// The settings the run will use, with the thread count resolved.
+    pub fn spec(&self) -> RunSpec {
+        RunSpec {
+            threads: self.resolved_threads(),
+            ..self.spec.clone()
+        }
+    }
+
+    /// Runs until a duration or iteration limit is reached.
+    /// Fails without starting if a setting is invalid, such as an empty CPU list.
+    pub fn run(self) -> Result<RunResult, String> {
+        self.run_until(&AtomicBool::new(false))
+    }
+
+    /// Runs until a limit is reached or `interrupted` is set.
+    pub fn run_until(self, interrupted: &AtomicBool) -> Result<RunResult, String> {
+        let spec = self.spec();
+        let outputs = RunOutputs {
+            run: RunInfo {
+                run_id: self.run_id.unwrap_or_else(sink::new_run_id),
+                threads: spec.threads,
+                mode: spec.workload.name.to_string(),
+            },
+            observers: self.observers,
+            metrics_listener: self.metrics_listener,
+            quiet: self.quiet,
+        };
+        runner::run(&spec, outputs, interrupted)
+    }
+
+    fn resolved_threads(&self) -> usize {
+        match (self.threads, &self.spec.pinned_cpus) {
+            (Some(threads), _) => threads,
+            (None, Some(cpus)) => cpus.len(),
+            (None, None) => thread::available_parallelism().map_or(1, |n| n.get()),
+        }
+    }
+}
@@ -0,0 +1,1078 @@
+use std::{ffi::OsString, net::SocketAddr, time::Duration};
+
+use string_repeater::affinity::PinPolicy;
+use string_repeater::corpus::Selection;
+use string_repeater::counter::CounterMode;
+use string_repeater::generate::{self, Charset};
+use string_repeater::metrics;
+use string_repeater::sink::OutputFormat;
+use string_repeater::workload::{self, WorkloadInfo, DEFAULT_WORKLOAD, WORKLOADS};
+
+use crate::config_file;
+
+pub const LOG_FILE_PATH: &str = "stats.log";
+pub const LOG_UPDATE_INTERVAL_MS: u64 = 1000; // Update log every 1000ms (1 second)
+pub const DEFAULT_TOLERANCE_PERCENT: f64 = 5.0; // Allowed slowdown against a baseline
+pub const DEFAULT_SWEEP_SIZES: &str = "8B..1MiB"; // Powers of two from 8 bytes to 1 MiB
+pub const LOG_LINE_WIDTH: usize = 192; // Snapshot record size in bytes, including the newline
+pub const ENV_PREFIX: &str = "STRING_REPEATER_"; // Environment variables overriding settings
+
+/// Settings for a single run, filled in from the command line.
+#[derive(Clone)]
+pub struct Config {
+    /// The string to repeat; `None` means read it from stdin, interactively on a terminal.
+    pub input: Option<String>,
+    /// Read the input from this file, or from stdin for `-`.
+    pub input_file: Option<String>,
+    /// Strip leading and trailing whitespace from the input.
+    pub trim: bool,
+    /// Process the entries of this corpus file or directory instead of one string.
+    pub corpus_path: Option<String>,
+    /// How workers pick corpus entries.
+    pub selection: Selection,
+    /// Weight of each corpus entry for weighted selection; `None` means equal weights.
+    pub weights: Option<Vec<f64>>,
+    /// Generate an input of this many bytes instead of taking a string.
+    pub size: Option<usize>,
+    /// Characters of generated inputs.
+    pub charset: Charset,
+    /// Seed of generated inputs.
+    pub seed: u64,
+    /// Number of worker threads; `None` means one per pinned CPU, or per available CPU.
+    pub threads: Option<usize>,
+    pub pin: PinPolicy,
+    /// Stop automatically after this long; `None` means no time limit.
+    pub duration: Option<Duration>,
+    /// Run uncounted for this long before measuring; `None` means not given, which is
+    /// no warmup for a single run but a default warmup for each sweep cell.
+    pub warmup: Option<Duration>,
+    /// Stop automatically after this many iterations; `None` means no iteration limit.
+    pub iterations: Option<usize>,
+    pub log_path: String,
+    /// Keep the latest sample in `log_path`, rewritten in place.
+    pub snapshot: bool,
+    /// Write the final JSON report to this file.
+    pub report_path: Option<String>,
+    /// Print the final JSON report to stdout.
+    pub report_stdout: bool,
+    /// Compare the run against the final report stored in this file.
+    pub baseline_path: Option<String>,
+    /// Allowed slowdown against the baseline, as a fraction.
+    pub tolerance: f64,
+    /// Serve Prometheus metrics over HTTP on this address.
+    pub metrics_addr: Option<SocketAddr>,
+    /// Format of the live statistics on the console and in the log files.
+    pub format: OutputFormat,
+    /// Snapshot record width in bytes, including the newline; `None` means wide
+    /// enough for the format.
+    pub log_width: Option<usize>,
+    /// Append a timestamped record per sample to this file.
+    pub series_path: Option<String>,
+    /// Write every sample as a CSV row to this file.
+    pub csv_path: Option<String>,
+    pub interval: Duration,
+    pub workload: &'static WorkloadInfo,
+    pub counter_mode: CounterMode,
+    /// Iterations a worker counts locally before publishing them to its counter.
+    pub batch_size: usize,
+    /// Run a matrix of thread counts and generated input sizes instead of a single run.
+    pub sweep: bool,
+    /// Thread counts to sweep; `None` means powers of two up to the available parallelism.
+    pub sweep_threads: Option<Vec<usize>>,
+    /// Input sizes in bytes to sweep.
+    pub sweep_sizes: Vec<usize>,
+    /// Suppress progress messages and live statistics on the console.
+    pub quiet: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            input: None,
+            input_file: None,
+            trim: false,
+            corpus_path: None,
+            selection: Selection::RoundRobin,
+            weights: None,
+            size: None,
+            charset: Charset::Ascii,
+            seed: generate::DEFAULT_SEED,
+            threads: None,
+            pin: PinPolicy::None,
+            duration: None,
+            iterations: None,
+            warmup: None,
+            log_path: LOG_FILE_PATH.to_string(),
+            snapshot: true,
+            log_width: None,
+            format: OutputFormat::Text,
+            metrics_addr: None,
+            report_path: None,
+            report_stdout: false,
+            baseline_path: None,
+            tolerance: DEFAULT_TOLERANCE_PERCENT / 100.0,
+            series_path: None,
+            csv_path: None,
+            interval: Duration::from_millis(LOG_UPDATE_INTERVAL_MS),
+            workload: workload::find(DEFAULT_WORKLOAD).expect("default workload is registered"),
+            counter_mode: CounterMode::Sharded,
+            batch_size: 1,
+            sweep: false,
+            sweep_threads: None,
+            sweep_sizes: parse_size_list(DEFAULT_SWEEP_SIZES).expect("default sweep sizes are valid"),
+            quiet: false,
+        }
+    }
+}
+
+/// What the command line asked the program to do.
+pub enum Command {
+    Run(Box<Config>),
+    Help,
+    Version,
+    ListModes,
+}
+
+/// Description of a long option, used for parsing and for the usage text.
+pub struct OptionSpec {
+    pub name: &'static str,
+    pub short: Option<char>,
+    /// Placeholder for the option's value, or `None` for a boolean flag.
+    pub value: Option<&'static str>,
+    pub help: &'static str,
+}
+
+pub const OPTIONS: &[OptionSpec] = &[
+    OptionSpec {
+        name: "config",
+        short: Some('c'),
+        value: Some("PATH"),
+        help: "read settings from a TOML file; command-line options take precedence",
+    },
+    OptionSpec {
+        name: "profile",
+        short: Some('p'),
+        value: Some("NAME"),
+        help: "also apply the [profile.NAME] table of the config file",
+    },
+    OptionSpec {
+        name: "input-file",
+        short: None,
+        value: Some("PATH"),
+        help: "repeat the exact contents of PATH, or of stdin for '-', instead of STRING",
+    },
+    OptionSpec {
+        name: "trim",
+        short: None,
+        value: None,
+        help: "strip leading and trailing whitespace from STRING or the input file",
+    },
+    OptionSpec {
+        name: "corpus",
+        short: None,
+        value: Some("PATH"),
+        help: "process the lines of file PATH, or the files of directory PATH, instead of STRING",
+    },
+    OptionSpec {
+        name: "select",
+        short: None,
+        value: Some("ORDER"),
+        help: "corpus entry order: round-robin, sequential or weighted (default: round-robin)",
+    },
+    OptionSpec {
+        name: "weights",
+        short: None,
+        value: Some("LIST"),
+        help: "weights of the corpus entries for --select weighted, e.g. 5,1,1 (default: equal)",
+    },
+    OptionSpec {
+        name: "size",
+        short: Some('s'),
+        value: Some("SIZE"),
+        help: "repeat a generated string of SIZE bytes, e.g. 64KiB, instead of STRING",
+    },
+    OptionSpec {
+        name: "charset",
+        short: None,
+        value: Some("NAME"),
+        help: "characters of generated strings: ascii, alnum, utf8, cjk, emoji or mixed (default: ascii)",
+    },
+    OptionSpec {
+        name: "seed",
+        short: None,
+        value: Some("N"),
+        help: "seed for generated strings (default: 42)",
+    },
+    OptionSpec {
+        name: "threads",
+        short: Some('t'),
+        value: Some("N"),
+        help: "number of worker threads (default: available parallelism)",
+    },
+    OptionSpec {
+        name: "pin",
+        short: None,
+        value: Some("CPUS"),
+        help: "pin workers to a CPU list like 0-3,8, 'physical' or 'none' (default: none)",
+    },
+    OptionSpec {
+        name: "duration",
+        short: Some('d'),
+        value: Some("TIME"),
+        help: "stop after TIME, e.g. 30s, 500ms, 2m (default: run until Ctrl+C)",
+    },
+    OptionSpec {
+        name: "iterations",
+        short: Some('n'),
+        value: Some("N"),
+        help: "stop after N iterations in total, e.g. 1e9 (default: run until Ctrl+C)",
+    },
+    OptionSpec {
+        name: "warmup",
+        short: Some('w'),
+        value: Some("TIME"),
+        help: "run for TIME before measuring; excluded from statistics (default: none)",
+    },
+    OptionSpec {
+        name: "log-path",
+        short: None,
+        value: Some("PATH"),
+        help: "statistics log file (default: stats.log)",
+    },
+    OptionSpec {
+        name: "format",
+        short: Some('f'),
+        value: Some("FORMAT"),
+        help: "statistics format for console and logs: 'text' or 'json' lines (default: text)",
+    },
+    OptionSpec {
+        name: "log-width",
+        short: None,
+        value: Some("BYTES"),
+        help: "pad each statistics log record to BYTES, newline included (default: 192, more for json)",
+    },
+    OptionSpec {
+        name: "no-snapshot",
+        short: None,
+        value: None,
+        help: "do not write the single-line statistics log file",
+    },
+    OptionSpec {
+        name: "series-log",
+        short: None,
+        value: Some("PATH"),
+        help: "append a timestamped record per interval to PATH",
+    },
+    OptionSpec {
+        name: "csv",
+        short: None,
+        value: Some("PATH"),
+        help: "write every interval sample as a CSV row to PATH",
+    },
+    OptionSpec {
+        name: "interval",
+        short: Some('i'),
+        value: Some("TIME"),
+        help: "statistics update interval (default: 1s)",
+    },
+    OptionSpec {
+        name: "mode",
+        short: Some('m'),
+        value: Some("NAME"),
+        help: "workload to run, see --list-modes (default: deep-clone)",
+    },
+    OptionSpec {
+        name: "counter",
+        short: None,
+        value: Some("MODE"),
+        help: "iteration counter: 'sharded' per worker or one 'shared' (default: sharded)",
+    },
+    OptionSpec {
+        name: "batch",
+        short: None,
+        value: Some("N"),
+        help: "count N iterations locally before publishing them (default: 1)",
+    },
+    OptionSpec {
+        name: "report",
+        short: None,
+        value: Some("PATH"),
+        help: "write a JSON final report to PATH",
+    },
+    OptionSpec {
+        name: "report-stdout",
+        short: None,
+        value: None,
+        help: "print only the JSON final report to stdout; the summary goes to stderr",
+    },
+    OptionSpec {
+        name: "baseline",
+        short: None,
+        value: Some("PATH"),
+        help: "compare against a previous --report and exit with status 3 on regression",
+    },
+    OptionSpec {
+        name: "tolerance",
+        short: None,
+        value: Some("PERCENT"),
+        help: "allowed slowdown against the baseline, e.g. 5 or 2.5% (default: 5)",
+    },
+    OptionSpec {
+        name: "metrics",
+        short: None,
+        value: Some("ADDR"),
+        help: "serve Prometheus metrics at http://ADDR/metrics; a bare port binds to 127.0.0.1",
+    },
+    OptionSpec {
+        name: "sweep",
+        short: None,
+        value: None,
+        help: "run every combination of --sweep-threads and --sweep-sizes",
+    },
+    OptionSpec {
+        name: "sweep-threads",
+        short: None,
+        value: Some("LIST"),
+        help: "thread counts to sweep, e.g. 1-4,8 (default: powers of two up to the CPU count)",
+    },
+    OptionSpec {
+        name: "sweep-sizes",
+        short: None,
+        value: Some("LIST"),
+        help: "input sizes to sweep, e.g. 64,1KiB or 8B..1MiB for powers of two (default: 8B..1MiB)",
+    },
+    OptionSpec {
+        name: "quiet",
+        short: Some('q'),
+        value: None,
+        help: "only print the final summary",
+    },
+];
+
+impl Config {
+    /// Checks combinations of settings that are invalid together.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.sweep {
+            // A sweep only reports its table and final report; reject outputs it would ignore
+            let single_run_options = [
+                ("--baseline", self.baseline_path.is_some()),
+                ("--log-path", self.log_path != LOG_FILE_PATH),
+                ("--format", self.format != OutputFormat::Text),
+                ("--log-width", self.log_width.is_some()),
+                ("--no-snapshot", !self.snapshot),
+                ("--series-log", self.series_path.is_some()),
+                ("--csv", self.csv_path.is_some()),
+                ("--metrics", self.metrics_addr.is_some()),
+            ];
+            if let Some((option, _)) = single_run_options.iter().find(|(_, given)| *given) {
+                return Err(format!("--sweep cannot be combined with {}", option));
+            }
+        }
+        let sources = [
+            self.input.is_some(),
+            self.input_file.is_some(),
+            self.corpus_path.is_some(),
+            self.size.is_some(),
+        ];
+        if sources.into_iter().filter(|&given| given).count() > 1 {
+            return Err("only one of STRING, --input-file, --corpus and --size can be given".to_string());
+        }
+        if self.weights.is_some() && (self.corpus_path.is_none() || self.selection != Selection::Weighted) {
+            return Err("--weights requires --corpus and --select weighted".to_string());
+        }
+        Ok(())
+    }
+
+    /// Applies a single named setting. Boolean settings take "true" or "false".
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        match name {
+            "input" => self.input = Some(value.to_string()),
+            "input-file" => self.input_file = Some(value.to_string()),
+            "trim" => self.trim = parse_bool(value).map_err(|e| format!("--trim: {}", e))?,
+            "corpus" => self.corpus_path = Some(value.to_string()),
+            "select" => self.selection = Selection::parse(value).map_err(|e| format!("--select: {}", e))?,
+            "weights" => self.weights = Some(parse_weights(value).map_err(|e| format!("--weights: {}", e))?),
+            "size" => {
+                let size = parse_size(value).map_err(|e| format!("--size: {}", e))?;
+                if size == 0 {
+                    return Err("--size: must be at least 1 byte".to_string());
+                }
+                self.size = Some(size);
+            }
+            "charset" => self.charset = Charset::parse(value).map_err(|e| format!("--charset: {}", e))?,
+            "seed" => self.seed = parse_count(value).map_err(|e| format!("--seed: {}", e))? as u64,
+            "threads" => {
+                let threads = parse_count(value).map_err(|e| format!("--threads: {}", e))?;
+                if threads == 0 {
+                    return Err("--threads: must be at least 1".to_string());
+                }
+                self.threads = Some(threads);
+            }
+            "pin" => self.pin = PinPolicy::parse(value).map_err(|e| format!("--pin: {}", e))?,
+            "duration" => {
+                self.duration = Some(parse_duration(value).map_err(|e| format!("--duration: {}", e))?);
+            }
+            "iterations" => {
+                let iterations = parse_count(value).map_err(|e| format!("--iterations: {}", e))?;
+                if iterations == 0 {
+                    return Err("--iterations: must be at least 1".to_string());
+                }
+                self.iterations = Some(iterations);
+            }
+            "warmup" => {
+                self.warmup = Some(parse_duration(value).map_err(|e| format!("--warmup: {}", e))?);
+            }
+            "log-path" => self.log_path = value.to_string(),
+            "format" => self.format = OutputFormat::parse(value).map_err(|e| format!("--format: {}", e))?,
+            "log-width" => {
+                let width = parse_count(value).map_err(|e| format!("--log-width: {}", e))?;
+                if width == 0 {
+                    return Err("--log-width: must be at least 1".to_string());
+                }
+                self.log_width = Some(width);
+            }
+            "no-snapshot" => {
+                self.snapshot = !parse_bool(value).map_err(|e| format!("--no-snapshot: {}", e))?;
+            }
+            "series-log" => self.series_path = Some(value.to_string()),
+            "csv" => self.csv_path = Some(value.to_string()),
+            "interval" => {
+                let interval = parse_duration(value).map_err(|e| format!("--interval: {}", e))?;
+                if interval.is_zero() {
+                    return Err("--interval: must be greater than zero".to_string());
+                }
+                self.interval = interval;
+            }
+            "mode" => {
+                self.workload = workload::find(value).ok_or_else(|| {
+                    format!("--mode: unknown workload \"{}\" (see --list-modes)", value)
+                })?;
+            }
+            "counter" => {
+                self.counter_mode = CounterMode::parse(value).map_err(|e| format!("--counter: {}", e))?;
+            }
+            "batch" => {
+                let batch_size = parse_count(value).map_err(|e| format!("--batch: {}", e))?;
+                if batch_size == 0 {
+                    return Err("--batch: must be at least 1".to_string());
+                }
+                self.batch_size = batch_size;
+            }
+            "report" => self.report_path = Some(value.to_string()),
+            "report-stdout" => {
+                self.report_stdout = parse_bool(value).map_err(|e| format!("--report-stdout: {}", e))?;
+            }
+            "baseline" => self.baseline_path = Some(value.to_string()),
+            "tolerance" => {
+                let percent: f64 = value
+                    .trim()
+                    .trim_end_matches('%')
+                    .parse()
+                    .map_err(|_| format!("--tolerance: invalid percentage \"{}\"", value))?;
+                if !(0.0..100.0).contains(&percent) {
+                    return Err("--tolerance: must be between 0 and 100".to_string());
+                }
+                self.tolerance = percent / 100.0;
+            }
+            "metrics" => {
+                self.metrics_addr = Some(metrics::parse_addr(value).map_err(|e| format!("--metrics: {}", e))?);
+            }
+            "sweep" => self.sweep = parse_bool(value).map_err(|e| format!("--sweep: {}", e))?,
+            "sweep-threads" => {
+                let threads = parse_count_list(value).map_err(|e| format!("--sweep-threads: {}", e))?;
+                if threads.contains(&0) {
+                    return Err("--sweep-threads: thread counts must be at least 1".to_string());
+                }
+                self.sweep_threads = Some(threads);
+            }
+            "sweep-sizes" => {
+                self.sweep_sizes = parse_size_list(value).map_err(|e| format!("--sweep-sizes: {}", e))?;
+            }
+            "quiet" => self.quiet = parse_bool(value).map_err(|e| format!("--quiet: {}", e))?,
+            _ => return Err(format!("unknown option --{}", name)),
+        }
+        Ok(())
+    }
+
+    /// Forgets every input source, so one given later replaces rather than conflicts with it.
+    fn clear_input(&mut self) {
+        self.input = None;
+        self.input_file = None;
+        self.corpus_path = None;
+        self.size = None;
+    }
+}
+
+/// Settings that choose where the input comes from; at most one can be in effect.
+const INPUT_SOURCES: [&str; 4] = ["input", "input-file", "corpus", "size"];
+
+/// Whether `name` can be given in a config file: the input, or any option other than
+/// those choosing the config file itself.
+fn is_setting(name: &str) -> bool {
+    name == "input"
+        || OPTIONS
+            .iter()
+            .any(|spec| spec.name == name && !matches!(name, "config" | "profile"))
+}
+
+/// Reads the `STRING_REPEATER_*` variables of `env` as `(variable, setting, value)`,
+/// sorted by variable name. `STRING_REPEATER_LOG_PATH` sets `log-path`; empty variables are ignored.
+fn env_settings<E: IntoIterator<Item = (OsString, OsString)>>(
+    env: E,
+) -> Result<Vec<(String, String, String)>, String> {
+    let mut settings = Vec::new();
+    for (variable, value) in env {
+        let Some(variable) = variable.to_str().filter(|variable| variable.starts_with(ENV_PREFIX)) else {
+            continue;
+        };
+        let value = value
+            .into_string()
+            .map_err(|_| format!("{} is not valid UTF-8", variable))?;
+        if value.is_empty() {
+            continue;
+        }
+        let name = variable[ENV_PREFIX.len()..].to_ascii_lowercase().replace('_', "-");
+        if !is_setting(&name) && !matches!(name.as_str(), "config" | "profile") {
+            return Err(format!("unknown environment variable {}", variable));
+        }
+        settings.push((variable.to_string(), name, value));
+    }
+    settings.sort();
+    Ok(settings)
+}
+
+/// Parses the program arguments (without the program name) and the environment.
+/// Settings are applied from the config file and its profile, then from
+/// `STRING_REPEATER_*` variables, then from the command line, so later sources win.
+pub fn parse_args<I, E>(args: I, env: E) -> Result<Command, String>
+where
+    I: IntoIterator<Item = String>,
+    E: IntoIterator<Item = (OsString, OsString)>,
+{
+    let mut args = args.into_iter();
+    let mut only_positional = false;
+    let mut config_path = None;
+    let mut profile = None;
+    let mut settings: Vec<(&str, String)> = Vec::new();
+
+    while let Some(arg) = args.next() {
+        if only_positional || arg == "-" || !arg.starts_with('-') {
+            if settings.iter().any(|(name, _)| *name == "input") {
+                return Err(format!("unexpected extra argument \"{}\"", arg));
+            }
+            settings.push(("input", arg));
+            continue;
+        }
+
+        let (spec, inline_value) = match arg.as_str() {
+            "--" => {
+                only_positional = true;
+                continue;
+            }
+            "-h" | "--help" => return Ok(Command::Help),
+            "-V" | "--version" => return Ok(Command::Version),
+            "--list-modes" => return Ok(Command::ListModes),
+            long if long.starts_with("--") => {
+                let (name, value) = match long[2..].split_once('=') {
+                    Some((name, value)) => (name, Some(value.to_string())),
+                    None => (&long[2..], None),
+                };
+                let spec = OPTIONS
+                    .iter()
+                    .find(|spec| spec.name == name)
+                    .ok_or_else(|| format!("unknown option --{}", name))?;
+                (spec, value)
+            }
+            short => {
+                let mut chars = short[1..].chars();
+                let flag = chars.next();
+                let rest: String = chars.collect();
+                let spec = OPTIONS
+                    .iter()
+                    .find(|spec| spec.short.is_some() && spec.short == flag)
+                    .ok_or_else(|| format!("unknown option {}", short))?;
+                (spec, if rest.is_empty() { None } else { Some(rest) })
+            }
+        };
+
+        let value = match (spec.value, inline_value) {
+            (Some(_), Some(value)) => value,
+            (Some(_), None) => args
+                .next()
+                .ok_or_else(|| format!("--{} requires a value", spec.name))?,
+            (None, Some(value)) => value,
+            (None, None) => "true".to_string(),
+        };
+        match spec.name {
+            "config" => config_path = Some(value),
+            "profile" => profile = Some(value),
+            name => settings.push((name, value)),
+        }
+    }
+
+    let mut env = env_settings(env)?;
+    for (_, name, value) in &env {
+        match name.as_str() {
+            "config" => config_path = config_path.or_else(|| Some(value.clone())),
+            "profile" => profile = profile.or_else(|| Some(value.clone())),
+            _ => {}
+        }
+    }
+    env.retain(|(_, name, _)| !matches!(name.as_str(), "config" | "profile"));
+
+    let mut config = Config::default();
+    match (&config_path, &profile) {
+        (Some(path), profile) => {
+            let layers = config_file::load(path, profile.as_deref())?;
+            for settings in [layers.base, layers.profile] {
+                if settings.iter().any(|setting| INPUT_SOURCES.contains(&setting.key.as_str())) {
+                    config.clear_input();
+                }
+                for setting in settings {
+                    if !is_setting(&setting.key) {
+                        return Err(format!("{}:{}: unknown setting \"{}\"", path, setting.line, setting.key));
+                    }
+                    config
+                        .set(&setting.key, &setting.value)
+                        .map_err(|e| format!("{}:{}: {}", path, setting.line, e))?;
+                }
+            }
+        }
+        (None, Some(_)) => return Err(format!("--profile requires --config or {}CONFIG", ENV_PREFIX)),
+        (None, None) => {}
+    }
+    if env.iter().any(|(_, name, _)| INPUT_SOURCES.contains(&name.as_str())) {
+        config.clear_input();
+    }
+    for (variable, name, value) in &env {
+        config.set(name, value).map_err(|e| format!("{}: {}", variable, e))?;
+    }
+    if settings.iter().any(|(name, _)| INPUT_SOURCES.contains(name)) {
+        config.clear_input();
+    }
+    for (name, value) in &settings {
+        config.set(name, value)?;
+    }
+
+    config.validate()?;
+    Ok(Command::Run(Box::new(config)))
+}
+
+/// Builds the `--help` text.
+pub fn usage() -> String {
+    let mut text = String::from(
+        "Usage: string_repeater [OPTIONS] [STRING]\n\n\
+         Repeatedly processes STRING on every CPU core and reports the repetition rate.\n\
+         If STRING is omitted, it is read interactively from a terminal or entirely from piped stdin.\n\nOptions:\n",
+    );
+    for spec in OPTIONS {
+        let short = match spec.short {
+            Some(short) => format!("-{}, ", short),
+            None => "    ".to_string(),
+        };
+        let long = match spec.value {
+            Some(value) => format!("--{} <{}>", spec.name, value),
+            None => format!("--{}", spec.name),
+        };
+        text.push_str(&format!("  {}{:<22} {}\n", short, long, spec.help));
+    }
+    text.push_str("      --list-modes           list the available workloads\n");
+    text.push_str("  -h, --help                 print this help\n");
+    text.push_str("  -V, --version              print the version\n");
+    text.push_str(&format!(
+        "\nEvery option can also be set as an environment variable such as {}LOG_PATH,\n\
+         which overrides the config file and is overridden by the command line.\n",
+        ENV_PREFIX
+    ));
+    text
+}
+
+/// Builds the `--list-modes` text.
+pub fn mode_list() -> String {
+    let mut text = String::new();
+    for workload in WORKLOADS {
+        text.push_str(&format!("{:<12} {}\n", workload.name, workload.description));
+    }
+    text
+}
+
+/// Parses a duration such as `30s`, `500ms`, `1.5m` or `2h`. A bare number is seconds.
+pub fn parse_duration(text: &str) -> Result<Duration, String> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration \"{}\"", text))?;
+    let seconds = match unit.trim() {
+        "" | "s" | "sec" | "secs" => number,
+        "ms" => number / 1000.0,
+        "m" | "min" | "mins" => number * 60.0,
+        "h" => number * 3600.0,
+        _ => return Err(format!("invalid duration unit in \"{}\"", text)),
+    };
+    Duration::try_from_secs_f64(seconds).map_err(|_| format!("invalid duration \"{}\"", text))
+}
+
+/// Parses a non-negative integer, allowing `_` separators and exponents like `1e9`.
+pub fn parse_count(text: &str) -> Result<usize, String> {
+    let invalid = || format!("invalid number \"{}\"", text);
+    let digits = text.trim().replace('_', "");
+    if let Ok(count) = digits.parse() {
+        return Ok(count);
+    }
+    if !digits.contains(['e', 'E']) {
+        return Err(invalid());
+    }
+    let value: f64 = digits.parse().map_err(|_| invalid())?;
+    if value < 0.0 || value.fract() != 0.0 || value > usize::MAX as f64 {
+        return Err(invalid());
+    }
+    Ok(value as usize)
+}
+
+/// Parses a comma-separated list of counts with inclusive ranges, e.g. `1-4,8,16`.
+pub fn parse_count_list(text: &str) -> Result<Vec<usize>, String> {
+    let mut counts = Vec::new();
+    for part in text.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+        match part.split_once('-') {
+            Some((first, last)) => {
+                let (first, last) = (parse_count(first)?, parse_count(last)?);
+                if first > last {
+                    return Err(format!("invalid range \"{}\"", part));
+                }
+                counts.extend(first..=last);
+            }
+            None => counts.push(parse_count(part)?),
+        }
+    }
+    if counts.is_empty() {
+        return Err(format!("empty list \"{}\"", text));
+    }
+    Ok(counts)
+}
+
+/// Parses a byte size such as `64`, `512B`, `64KiB`, `1.5MB` or `2GiB`.
+/// Decimal units (KB, MB, GB) are powers of 1000; binary units (K, KiB, ...) of 1024.
+pub fn parse_size(text: &str) -> Result<usize, String> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '_'))
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: f64 = number
+        .replace('_', "")
+        .parse()
+        .map_err(|_| format!("invalid size \"{}\"", text))?;
+    let multiplier: f64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1.0,
+        "kb" => 1e3,
+        "mb" => 1e6,
+        "gb" => 1e9,
+        "k" | "kib" => 1024.0,
+        "m" | "mib" => 1024.0 * 1024.0,
+        "g" | "gib" => 1024.0 * 1024.0 * 1024.0,
+        _ => return Err(format!("invalid size unit in \"{}\"", text)),
+    };
+    let bytes = number * multiplier;
+    if bytes.fract() != 0.0 || bytes > usize::MAX as f64 {
+        return Err(format!("invalid size \"{}\"", text));
+    }
+    Ok(bytes as usize)
+}
+
+/// Parses a comma-separated list of sizes, where `A..B` expands to the powers of two from A to B.
+pub fn parse_size_list(text: &str) -> Result<Vec<usize>, String> {
+    let mut sizes = Vec::new();
+    for part in text.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+        match part.split_once("..") {
+            Some((first, last)) => {
+                let (first, last) = (parse_size(first)?, parse_size(last)?);
+                if first == 0 || first > last {
+                    return Err(format!("invalid range \"{}\"", part));
+                }
+                let mut size = first;
+                while size <= last {
+                    sizes.push(size);
+                    size = match size.checked_mul(2) {
+                        Some(next) => next,
+                        None => break,
+                    };
+                }
+            }
+            None => sizes.push(parse_size(part)?),
+        }
+    }
+    if sizes.is_empty() || sizes.contains(&0) {
+        return Err(format!("invalid size list \"{}\"", text));
+    }
+    Ok(sizes)
+}
+
+/// Parses a comma-separated list of non-negative weights.
+fn parse_weights(text: &str) -> Result<Vec<f64>, String> {
+    text.split(',')
+        .map(|part| match part.trim().parse::<f64>() {
+            Ok(weight) if weight.is_finite() && weight >= 0.0 => Ok(weight),
+            _ => Err(format!("invalid weight \"{}\"", part.trim())),
+        })
+        .collect()
+}
+
+fn parse_bool(text: &str) -> Result<bool, String> {
+    match text.trim() {
+        "true" | "1" | "yes" | "on" => Ok(true),
+        "false" | "0" | "no" | "off" => Ok(false),
+        _ => Err(format!("invalid boolean \"{}\"", text)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(text: &[&str]) -> Vec<String> {
+        text.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    fn run_config(text: &[&str]) -> Result<Config, String> {
+        match parse_args(args(text), Vec::new())? {
+            Command::Run(config) => Ok(*config),
+            _ => panic!("expected a run"),
+        }
+    }
+
+    /// Writes `text` to a config file unique to this test, removed when dropped.
+    struct ConfigFile(std::path::PathBuf);
+
+    impl ConfigFile {
+        fn new(name: &str, text: &str) -> ConfigFile {
+            let path = std::env::temp_dir().join(format!("string_repeater_{}_{}.toml", name, std::process::id()));
+            std::fs::write(&path, text).unwrap();
+            ConfigFile(path)
+        }
+
+        fn path(&self) -> &str {
+            self.0.to_str().unwrap()
+        }
+    }
+
+    impl Drop for ConfigFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn profile_input_replaces_top_level_input() {
+        let file = ConfigFile::new("profile_input", "size = \"64\"\n[profile.quick]\ninput = \"hello\"\n");
+        let config = run_config(&["-c", file.path(), "-p", "quick"]).unwrap();
+        assert_eq!(config.input.as_deref(), Some("hello"));
+        assert_eq!(config.size, None);
+
+        let config = run_config(&["-c", file.path()]).unwrap();
+        assert_eq!(config.size, Some(64));
+    }
+
+    #[test]
+    fn command_line_overrides_profile_over_top_level() {
+        let file = ConfigFile::new(
+            "precedence",
+            "threads = 2\nmode = \"hash\"\nsize = \"1KiB\"\n[profile.p]\nthreads = 4\n",
+        );
+        let config = run_config(&["-c", file.path(), "-p", "p"]).unwrap();
+        assert_eq!((config.threads, config.workload.name), (Some(4), "hash"));
+
+        let config = run_config(&["-c", file.path(), "-p", "p", "-t", "8", "word"]).unwrap();
+        assert_eq!(config.threads, Some(8));
+        assert_eq!((config.input.as_deref(), config.size), (Some("word"), None));
+    }
+
+    #[test]
+    fn explicit_zero_warmup_differs_from_no_warmup() {
+        assert_eq!(run_config(&["x"]).unwrap().warmup, None);
+        assert_eq!(run_config(&["--warmup", "0", "x"]).unwrap().warmup, Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn parses_durations() {
+        let cases = [
+            ("30", Some(Duration::from_secs(30))),
+            ("30s", Some(Duration::from_secs(30))),
+            ("1.5secs", Some(Duration::from_millis(1500))),
+            ("500ms", Some(Duration::from_millis(500))),
+            ("2m", Some(Duration::from_secs(120))),
+            ("1.5min", Some(Duration::from_secs(90))),
+            ("2h", Some(Duration::from_secs(7200))),
+            (" 250 ms ", Some(Duration::from_millis(250))),
+            ("0", Some(Duration::ZERO)),
+            ("", None),
+            ("s", None),
+            ("10d", None),
+            ("-1s", None),
+            ("1.2.3s", None),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(parse_duration(text).ok(), expected, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn parses_counts() {
+        let cases = [
+            ("0", Some(0)),
+            ("42", Some(42)),
+            (" 7 ", Some(7)),
+            ("1_000_000", Some(1_000_000)),
+            ("1e9", Some(1_000_000_000)),
+            ("2.5E3", Some(2500)),
+            ("1.5", None),
+            ("1.5e0", None),
+            ("-1", None),
+            ("-1e3", None),
+            ("1e30", None),
+            ("ten", None),
+            ("", None),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(parse_count(text).ok(), expected, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn parses_count_lists() {
+        let cases = [
+            ("1", Some(vec![1])),
+            ("1-4,8", Some(vec![1, 2, 3, 4, 8])),
+            (" 2 , 4 ,", Some(vec![2, 4])),
+            ("4-1", None),
+            ("", None),
+            ("1,x", None),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(parse_count_list(text).ok(), expected, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn parses_sizes() {
+        let cases = [
+            ("64", Some(64)),
+            ("512B", Some(512)),
+            ("1_024", Some(1024)),
+            ("64KiB", Some(65_536)),
+            ("64k", Some(65_536)),
+            ("1.5MB", Some(1_500_000)),
+            ("1MiB", Some(1 << 20)),
+            ("2 GiB", Some(2 << 30)),
+            ("1GB", Some(1_000_000_000)),
+            ("0.5B", None),
+            ("1TB", None),
+            ("KiB", None),
+            ("", None),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(parse_size(text).ok(), expected, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn parses_size_lists() {
+        let cases = [
+            ("64,1KiB", Some(vec![64, 1024])),
+            ("8B..64B", Some(vec![8, 16, 32, 64])),
+            ("8..100", Some(vec![8, 16, 32, 64])),
+            ("1KiB..1KiB,3", Some(vec![1024, 3])),
+            ("0..8", None),
+            ("64..8", None),
+            ("0", None),
+            ("", None),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(parse_size_list(text).ok(), expected, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn accepts_every_option_form() {
+        let forms: [&[&str]; 6] = [
+            &["--threads", "4", "x"],
+            &["--threads=4", "x"],
+            &["-t", "4", "x"],
+            &["-t4", "x"],
+            &["x", "-t", "4"],
+            &["-t", "4", "--", "x"],
+        ];
+        for form in forms {
+            let config = run_config(form).unwrap();
+            assert_eq!((config.threads, config.input.as_deref()), (Some(4), Some("x")), "{:?}", form);
+        }
+
+        let config = run_config(&["-n", "1e9", "-d", "1.5m", "--quiet", "--no-snapshot", "--", "-dash"]).unwrap();
+        assert_eq!(config.iterations, Some(1_000_000_000));
+        assert_eq!(config.duration, Some(Duration::from_secs(90)));
+        assert!(config.quiet && !config.snapshot);
+        assert_eq!(config.input.as_deref(), Some("-dash"));
+
+        assert_eq!(run_config(&["-"]).unwrap().input.as_deref(), Some("-"));
+        assert!(!run_config(&["--quiet=false"]).unwrap().quiet);
+    }
+
+    #[test]
+    fn returns_commands_for_informational_flags() {
+        assert!(matches!(parse_args(args(&["-h"]), Vec::new()), Ok(Command::Help)));
+        assert!(matches!(parse_args(args(&["x", "--version"]), Vec::new()), Ok(Command::Version)));
+        assert!(matches!(parse_args(args(&["--list-modes"]), Vec::new()), Ok(Command::ListModes)));
+    }
+
+    #[test]
+    fn rejects_invalid_arguments() {
+        let cases: [&[&str]; 19] = [
+            &["--bogus"],
+            &["-z"],
+            &["--threads"],
+            &["--threads", "0", "x"],
+            &["--iterations", "0", "x"],
+            &["--interval", "0", "x"],
+            &["--mode", "nope", "x"],
+            &["a", "b"],
+            &["x", "--size", "64"],
+            &["--weights", "1,2", "x"],
+            &["--sweep", "--baseline", "b.json"],
+            &["--sweep", "--csv", "samples.csv"],
+            &["--sweep", "--series-log", "series.log"],
+            &["--sweep", "--metrics", "9100"],
+            &["--sweep", "--format", "json"],
+            &["--sweep", "--log-path", "other.log"],
+            &["--sweep", "--log-width", "256"],
+            &["--sweep", "--no-snapshot"],
+            &["--quiet=maybe"],
+        ];
+        for case in cases {
+            assert!(parse_args(args(case), Vec::new()).is_err(), "{:?}", case);
+        }
+    }
+
+    #[test]
+    fn environment_overrides_config_file_and_is_overridden_by_arguments() {
+        let env = |pairs: &[(&str, &str)]| -> Vec<(OsString, OsString)> {
+            pairs.iter().map(|(name, value)| (name.into(), value.into())).collect()
+        };
+        let file = ConfigFile::new("environment", "threads = 2\nmode = \"hash\"\n");
+        let parse = |text: &[&str], pairs: &[(&str, &str)]| match parse_args(args(text), env(pairs)) {
+            Ok(Command::Run(config)) => Ok(config),
+            Ok(_) => panic!("expected a run"),
+            Err(e) => Err(e),
+        };
+
+        let config = parse(&["x"], &[("STRING_REPEATER_CONFIG", file.path()), ("STRING_REPEATER_THREADS", "3")]).unwrap();
+        assert_eq!((config.threads, config.workload.name), (Some(3), "hash"));
+        let config = parse(&["-t", "5", "x"], &[("STRING_REPEATER_THREADS", "3")]).unwrap();
+        assert_eq!(config.threads, Some(5));
+        let config = parse(&["x"], &[("STRING_REPEATER_THREADS", ""), ("OTHER", "1")]).unwrap();
+        assert_eq!(config.threads, None);
+        assert!(parse(&["x"], &[("STRING_REPEATER_THREDS", "3")]).is_err());
+    }
+}
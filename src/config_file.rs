@@ -0,0 +1,514 @@
+use std::fs;
+
+/// One `key = value` setting read from a config file.
+pub struct Setting {
+    /// The option name, with `_` written as `-`.
+    pub key: String,
+    /// The value as it would be given on the command line; arrays are joined with commas.
+    pub value: String,
+    pub line: usize,
+}
+
+/// The top-level settings of a config file and its `[profile.NAME]` tables.
+struct Document {
+    base: Vec<Setting>,
+    profiles: Vec<(String, Vec<Setting>)>,
+}
+
+/// The settings read from a config file, in the order they are applied.
+pub struct Layers {
+    /// The top-level settings.
+    pub base: Vec<Setting>,
+    /// The settings of the selected profile, which override the top-level ones.
+    pub profile: Vec<Setting>,
+}
+
+/// Reads the top-level settings of the file at `path` and those of `profile`, if given.
+pub fn load(path: &str, profile: Option<&str>) -> Result<Layers, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("cannot read config file {}: {}", path, e))?;
+    let document = parse(&text).map_err(|(line, e)| format!("{}:{}: {}", path, line, e))?;
+    select(document, profile).map_err(|e| format!("{} in {}", e, path))
+}
+
+/// Splits a parsed document into its top-level settings and those of `profile`.
+fn select(document: Document, profile: Option<&str>) -> Result<Layers, String> {
+    let mut layers = Layers {
+        base: document.base,
+        profile: Vec::new(),
+    };
+    if let Some(profile) = profile {
+        let mut profiles = document.profiles;
+        let index = profiles.iter().position(|(name, _)| name == profile).ok_or_else(|| {
+            let names: Vec<&str> = profiles.iter().map(|(name, _)| name.as_str()).collect();
+            format!(
+                "profile \"{}\" not found (available: {})",
+                profile,
+                if names.is_empty() { "none".to_string() } else { names.join(", ") }
+            )
+        })?;
+        layers.profile = profiles.swap_remove(index).1;
+    }
+    Ok(layers)
+}
+
+/// Parses the subset of TOML used by config files: comments, `[profile.NAME]`
+/// headers, and `key = value` settings whose value is a string, number, boolean
+/// or array of those, which may span lines. Errors carry their line number.
+fn parse(text: &str) -> Result<Document, (usize, String)> {
+    let mut document = Document {
+        base: Vec::new(),
+        profiles: Vec::new(),
+    };
+    // Index into `document.profiles` of the current table, or `None` for the top level
+    let mut table: Option<usize> = None;
+
+    let mut cursor = Cursor::new(text);
+    while cursor.next_line() {
+        let line_number = cursor.line_number;
+        cursor.skip_whitespace();
+        if cursor.at_end_of_line() {
+            continue;
+        }
+
+        if cursor.eat('[') {
+            let name = profile_header(&mut cursor).map_err(|e| (line_number, e))?;
+            if document.profiles.iter().any(|(existing, _)| *existing == name) {
+                return Err((line_number, format!("profile \"{}\" is defined twice", name)));
+            }
+            document.profiles.push((name, Vec::new()));
+            table = Some(document.profiles.len() - 1);
+            continue;
+        }
+
+        let key = cursor.key().map_err(|e| (line_number, e))?;
+        if !cursor.eat('=') {
+            return Err((line_number, format!("expected \"=\" after \"{}\"", key)));
+        }
+        let value = cursor.value().map_err(|e| (cursor.line_number, e))?;
+        if !cursor.rest_is_blank() {
+            return Err((cursor.line_number, format!("unexpected text after the value of \"{}\"", key)));
+        }
+
+        let settings = match table {
+            Some(index) => &mut document.profiles[index].1,
+            None => &mut document.base,
+        };
+        let key = key.replace('_', "-");
+        if settings.iter().any(|setting| setting.key == key) {
+            return Err((line_number, format!("duplicate key \"{}\"", key)));
+        }
+        settings.push(Setting {
+            key,
+            value,
+            line: line_number,
+        });
+    }
+    Ok(document)
+}
+
+/// Reads the name of a `[profile.NAME]` header after its opening bracket.
+fn profile_header(cursor: &mut Cursor) -> Result<String, String> {
+    let header = cursor.rest.trim();
+    let unknown = || {
+        unsupported(&format!(
+            "unknown table [{}] (expected [profile.NAME])",
+            header.trim_end_matches(']').trim()
+        ))
+    };
+    if cursor.peek() == Some('[') {
+        return Err(unsupported("arrays of tables are not supported"));
+    }
+    cursor.skip_whitespace();
+    if cursor.take_while(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') != "profile" {
+        return Err(unknown());
+    }
+    cursor.skip_whitespace();
+    if !cursor.eat('.') {
+        return Err(unknown());
+    }
+    let name = cursor.key_part()?;
+    cursor.skip_whitespace();
+    if cursor.peek() == Some('.') {
+        return Err(unknown());
+    }
+    if !cursor.eat(']') || !cursor.rest_is_blank() {
+        return Err(format!("invalid table header \"[{}\"", header));
+    }
+    Ok(name)
+}
+
+fn is_bare_key(key: &str) -> bool {
+    !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Adds a pointer to the documented subset of TOML to a syntax error.
+fn unsupported(message: &str) -> String {
+    format!(
+        "{}; config files use a subset of TOML, see \"Configuration Files\" in the README",
+        message
+    )
+}
+
+/// Reads a config file line by line; arrays may continue on the following lines.
+struct Cursor<'a> {
+    lines: std::str::Lines<'a>,
+    /// Number of the current line, starting at 1.
+    line_number: usize,
+    /// The unread part of the current line.
+    rest: &'a str,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(text: &'a str) -> Cursor<'a> {
+        Cursor {
+            lines: text.lines(),
+            line_number: 0,
+            rest: "",
+        }
+    }
+
+    /// Moves to the start of the next line; returns `false` at the end of the text.
+    fn next_line(&mut self) -> bool {
+        match self.lines.next() {
+            Some(line) => {
+                self.rest = line;
+                self.line_number += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest.chars().next()
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.rest = &self.rest[expected.len_utf8()..];
+            true
+        } else {
+            false
+        }
+    }
+
+    fn take_while(&mut self, keep: impl Fn(char) -> bool) -> &'a str {
+        let end = self.rest.find(|c| !keep(c)).unwrap_or(self.rest.len());
+        let (taken, rest) = self.rest.split_at(end);
+        self.rest = rest;
+        taken
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.take_while(|c| c == ' ' || c == '\t');
+    }
+
+    fn at_end_of_line(&self) -> bool {
+        self.rest.is_empty() || self.rest.starts_with('#')
+    }
+
+    /// Whether only whitespace and a comment are left.
+    fn rest_is_blank(&mut self) -> bool {
+        self.skip_whitespace();
+        self.at_end_of_line()
+    }
+
+    /// Skips whitespace, comments and line breaks; returns `false` at the end of the text.
+    fn skip_blank_lines(&mut self) -> bool {
+        while self.rest_is_blank() {
+            if !self.next_line() {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Reads the key of a setting, up to the `=`.
+    fn key(&mut self) -> Result<String, String> {
+        let key = self.key_part()?;
+        self.skip_whitespace();
+        if self.peek() == Some('.') {
+            return Err(unsupported("dotted keys are not supported"));
+        }
+        Ok(key)
+    }
+
+    /// Reads a bare or quoted key, or the name in a table header.
+    fn key_part(&mut self) -> Result<String, String> {
+        self.skip_whitespace();
+        if self.eat('"') {
+            return self.basic_string();
+        }
+        if self.eat('\'') {
+            return self.literal_string();
+        }
+        let key = self.take_while(|c| !matches!(c, '=' | '.' | ']' | '#')).trim_end();
+        if !is_bare_key(key) {
+            return Err(format!("invalid key \"{}\"", key));
+        }
+        Ok(key.to_string())
+    }
+
+    /// Reads a value and renders it as command-line text.
+    fn value(&mut self) -> Result<String, String> {
+        self.skip_whitespace();
+        if self.eat('[') {
+            let start = self.line_number;
+            let unterminated = || format!("unterminated array starting on line {}", start);
+            let mut items = Vec::new();
+            loop {
+                if !self.skip_blank_lines() {
+                    return Err(unterminated());
+                }
+                if self.eat(']') {
+                    break;
+                }
+                items.push(self.scalar()?);
+                if !self.skip_blank_lines() {
+                    return Err(unterminated());
+                }
+                if !self.eat(',') && self.peek() != Some(']') {
+                    return Err("expected \",\" or \"]\" in array".to_string());
+                }
+            }
+            return Ok(items.join(","));
+        }
+        self.scalar()
+    }
+
+    /// Reads a string, number or boolean.
+    fn scalar(&mut self) -> Result<String, String> {
+        self.skip_whitespace();
+        if self.rest.starts_with("\"\"\"") || self.rest.starts_with("\'\'\'") {
+            return Err(unsupported("multi-line strings are not supported"));
+        }
+        if self.eat('"') {
+            return self.basic_string();
+        }
+        if self.eat('\'') {
+            return self.literal_string();
+        }
+        if self.peek() == Some('{') {
+            return Err(unsupported("inline tables are not supported"));
+        }
+        let token = self.take_while(|c| !matches!(c, ' ' | '\t' | ',' | ']' | '#'));
+        let is_number = token.starts_with(|c: char| c.is_ascii_digit() || c == '+' || c == '-')
+            && token
+                .chars()
+                .all(|c| c.is_ascii_digit() || matches!(c, '_' | '.' | 'e' | 'E' | '+' | '-'));
+        if token == "true" || token == "false" || is_number {
+            Ok(token.trim_start_matches('+').to_string())
+        } else if token.is_empty() {
+            Err("missing value".to_string())
+        } else {
+            Err(format!("invalid value \"{}\" (strings must be quoted)", token))
+        }
+    }
+
+    /// Reads the rest of a single-quoted string, which has no escapes, after the opening quote.
+    fn literal_string(&mut self) -> Result<String, String> {
+        let text = self.take_while(|c| c != '\'');
+        if !self.eat('\'') {
+            return Err("unterminated string".to_string());
+        }
+        Ok(text.to_string())
+    }
+
+    /// Reads the rest of a double-quoted string after the opening quote.
+    fn basic_string(&mut self) -> Result<String, String> {
+        let mut text = String::new();
+        let mut chars = self.rest.char_indices();
+        while let Some((position, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.rest = &self.rest[position + 1..];
+                    return Ok(text);
+                }
+                '\\' => {
+                    let escaped = match chars.next().map(|(_, c)| c) {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('r') => '\r',
+                        Some('u') => {
+                            let digits: String = chars.by_ref().take(4).map(|(_, c)| c).collect();
+                            u32::from_str_radix(&digits, 16)
+                                .ok()
+                                .and_then(char::from_u32)
+                                .ok_or_else(|| format!("invalid escape \"\\u{}\"", digits))?
+                        }
+                        other => {
+                            return Err(format!(
+                                "invalid escape \"\\{}\"",
+                                other.map(String::from).unwrap_or_default()
+                            ))
+                        }
+                    };
+                    text.push(escaped);
+                }
+                c => text.push(c),
+            }
+        }
+        Err("unterminated string".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(settings: &[Setting]) -> Vec<(&str, &str)> {
+        settings
+            .iter()
+            .map(|setting| (setting.key.as_str(), setting.value.as_str()))
+            .collect()
+    }
+
+    fn error_line(text: &str) -> usize {
+        match parse(text) {
+            Ok(_) => panic!("expected an error for {:?}", text),
+            Err((line, _)) => line,
+        }
+    }
+
+    #[test]
+    fn skips_comments_and_blank_lines() {
+        let document = parse("# heading\n\n  mode = \"hash\"  # trailing\n\t\nthreads = 4\n").unwrap();
+        assert_eq!(pairs(&document.base), [("mode", "hash"), ("threads", "4")]);
+        assert_eq!(document.base[1].line, 5);
+    }
+
+    #[test]
+    fn reads_scalars_as_command_line_text() {
+        let document = parse(
+            "a = \"30s\"\nb = '64KiB'\nc = 1_000\nd = +2.5\ne = -1e9\nf = true\ng = false\nlog_path = \"x\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            pairs(&document.base),
+            [
+                ("a", "30s"),
+                ("b", "64KiB"),
+                ("c", "1_000"),
+                ("d", "2.5"),
+                ("e", "-1e9"),
+                ("f", "true"),
+                ("g", "false"),
+                ("log-path", "x"),
+            ]
+        );
+    }
+
+    #[test]
+    fn decodes_escapes_in_basic_strings_only() {
+        let document = parse(r#"a = "q\" b\\ \t\n \u00e9 # not a comment"
+b = 'C:\raw\n'
+"#)
+        .unwrap();
+        assert_eq!(
+            pairs(&document.base),
+            [("a", "q\" b\\ \t\n é # not a comment"), ("b", "C:\\raw\\n")]
+        );
+        assert_eq!(error_line("a = \"\\x\""), 1);
+        assert_eq!(error_line("a = \"\\uD800\""), 1);
+        assert_eq!(error_line("\na = \"open"), 2);
+        assert_eq!(error_line("a = 'open"), 1);
+    }
+
+    #[test]
+    fn joins_arrays_with_commas() {
+        let document = parse("a = [1, 2, 4]\nb = [\"64B\", '1MiB',]\nc = []\n").unwrap();
+        assert_eq!(pairs(&document.base), [("a", "1,2,4"), ("b", "64B,1MiB"), ("c", "")]);
+        assert_eq!(error_line("a = [1, 2"), 1);
+        assert_eq!(error_line("a = [1 2]"), 1);
+    }
+
+    #[test]
+    fn reads_arrays_across_lines() {
+        let text = "sweep-sizes = [\n  \"8B\",  # smallest\n\n  \"1KiB\"\n  , \"1MiB\",\n]\nthreads = 2\n";
+        let document = parse(text).unwrap();
+        assert_eq!(pairs(&document.base), [("sweep-sizes", "8B,1KiB,1MiB"), ("threads", "2")]);
+        assert_eq!((document.base[0].line, document.base[1].line), (1, 7));
+        assert_eq!(error_line("a = [\n1,\n2"), 3);
+        assert_eq!(error_line("a = [\n1\n2]"), 3);
+        assert_eq!(error_line("a = [\n1,\n] 2"), 3);
+    }
+
+    #[test]
+    fn reads_quoted_keys_and_profile_names() {
+        let text = "\"threads\" = 2\n'log_path' = \"x\"\n[profile.\"ci run\"]\nmode = \"hash\"\n[ profile . 'soak' ]\n";
+        let document = parse(text).unwrap();
+        assert_eq!(pairs(&document.base), [("threads", "2"), ("log-path", "x")]);
+        let names: Vec<&str> = document.profiles.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["ci run", "soak"]);
+    }
+
+    #[test]
+    fn names_the_supported_subset_for_other_toml() {
+        let cases = [
+            "a = \"\"\"\ntext\n\"\"\"",
+            "a = '''text'''",
+            "profile.quick.threads = 2",
+            "\"profile\".threads = 2",
+            "a = { b = 1 }",
+            "[[profile]]",
+            "[other]",
+            "[profile.a.b]",
+        ];
+        for text in cases {
+            match parse(text) {
+                Ok(_) => panic!("expected an error for {:?}", text),
+                Err((line, message)) => {
+                    assert_eq!(line, 1, "{:?}", text);
+                    assert!(message.contains("subset of TOML"), "{:?}: {}", text, message);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_lines() {
+        assert_eq!(error_line("mode = hash"), 1);
+        assert_eq!(error_line("mode ="), 1);
+        assert_eq!(error_line("mode"), 1);
+        assert_eq!(error_line("bad key = 1"), 1);
+        assert_eq!(error_line("a = 1 2"), 1);
+        assert_eq!(error_line("[other]"), 1);
+        assert_eq!(error_line("[profile.]"), 1);
+        assert_eq!(error_line("[profile.quick"), 1);
+        assert_eq!(error_line("[profile.\"open]"), 1);
+    }
+
+    #[test]
+    fn rejects_duplicates_within_a_table() {
+        assert_eq!(error_line("a = 1\na = 2"), 2);
+        assert_eq!(error_line("log-path = \"a\"\nlog_path = \"b\""), 2);
+        assert_eq!(error_line("[profile.a]\n[profile.a]"), 2);
+        // The same key at the top level and in a profile is an override
+        assert!(parse("a = 1\n[profile.p]\na = 2\n").is_ok());
+    }
+
+    #[test]
+    fn collects_profile_tables() {
+        let document = parse("a = 1\n[profile.quick]\nb = 2\n[ profile.alloc-stress ]\nc = 3\n").unwrap();
+        assert_eq!(pairs(&document.base), [("a", "1")]);
+        let names: Vec<&str> = document.profiles.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["quick", "alloc-stress"]);
+        assert_eq!(pairs(&document.profiles[1].1), [("c", "3")]);
+    }
+
+    #[test]
+    fn selects_base_then_profile() {
+        let text = "threads = 2\nmode = \"hash\"\n[profile.quick]\nthreads = 8\n[profile.soak]\nduration = \"1h\"\n";
+        let layers = select(parse(text).unwrap(), Some("quick")).unwrap();
+        assert_eq!(pairs(&layers.base), [("threads", "2"), ("mode", "hash")]);
+        assert_eq!(pairs(&layers.profile), [("threads", "8")]);
+
+        let layers = select(parse(text).unwrap(), None).unwrap();
+        assert!(layers.profile.is_empty());
+
+        let error = select(parse(text).unwrap(), Some("nope")).err().unwrap();
+        assert!(error.contains("quick, soak"), "{}", error);
+    }
+}
@@ -0,0 +1,299 @@
+use std::{fs, io, path::Path, sync::Arc};
+
+use crate::generate::SplitMix64;
+
+/// How each worker picks the next corpus entry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Selection {
+    /// Walk the corpus in order, each worker starting at a different entry.
+    RoundRobin,
+    /// Walk the corpus in order, every worker starting at the first entry.
+    Sequential,
+    /// Draw entries at random in proportion to their weights.
+    Weighted,
+}
+
+impl Selection {
+    pub fn name(self) -> &'static str {
+        match self {
+            Selection::RoundRobin => "round-robin",
+            Selection::Sequential => "sequential",
+            Selection::Weighted => "weighted",
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Selection, String> {
+        match text.trim() {
+            "round-robin" => Ok(Selection::RoundRobin),
+            "sequential" => Ok(Selection::Sequential),
+            "weighted" => Ok(Selection::Weighted),
+            _ => Err(format!(
+                "unknown selection \"{}\" (expected round-robin, sequential or weighted)",
+                text
+            )),
+        }
+    }
+}
+
+/// The strings a run processes, shared by all workers.
+pub struct Corpus {
+    entries: Vec<Arc<String>>,
+    /// Running totals of the entry weights; empty means equal weights.
+    cumulative_weights: Vec<f64>,
+}
+
+impl Corpus {
+    /// A corpus of one string.
+    pub fn single(input: String) -> Corpus {
+        Corpus {
+            entries: vec![Arc::new(input)],
+            cumulative_weights: Vec::new(),
+        }
+    }
+
+    /// Loads a corpus from a file with one entry per line, or from a directory
+    /// with one entry per file. Empty lines and files are skipped.
+    pub fn load(path: &str) -> io::Result<Corpus> {
+        let entries: Vec<String> = if Path::new(path).is_dir() {
+            let mut paths = Vec::new();
+            for dir_entry in fs::read_dir(path)? {
+                let dir_entry = dir_entry?;
+                if dir_entry.file_type()?.is_file() {
+                    paths.push(dir_entry.path());
+                }
+            }
+            paths.sort();
+            paths
+                .iter()
+                .map(fs::read_to_string)
+                .collect::<io::Result<Vec<String>>>()?
+        } else {
+            fs::read_to_string(path)?.lines().map(str::to_string).collect()
+        };
+
+        let entries: Vec<Arc<String>> = entries
+            .into_iter()
+            .filter(|entry| !entry.is_empty())
+            .map(Arc::new)
+            .collect();
+        if entries.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "the corpus is empty"));
+        }
+        Ok(Corpus {
+            entries,
+            cumulative_weights: Vec::new(),
+        })
+    }
+
+    /// Sets the weight of each entry for weighted selection.
+    pub fn set_weights(&mut self, weights: &[f64]) -> Result<(), String> {
+        if weights.len() != self.entries.len() {
+            return Err(format!(
+                "got {} weights for {} corpus entries",
+                weights.len(),
+                self.entries.len()
+            ));
+        }
+        if weights.iter().sum::<f64>() <= 0.0 {
+            return Err("at least one weight must be positive".to_string());
+        }
+        self.cumulative_weights = weights
+            .iter()
+            .scan(0.0, |total, weight| {
+                *total += weight;
+                Some(*total)
+            })
+            .collect();
+        Ok(())
+    }
+
+    pub fn entries(&self) -> &[Arc<String>] {
+        &self.entries
+    }
+
+    pub fn entry_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Sum of the entry lengths in bytes.
+    pub fn total_bytes(&self) -> usize {
+        self.entries.iter().map(|entry| entry.len()).sum()
+    }
+
+    /// The longest entry, used to size per-thread workload buffers.
+    pub fn largest(&self) -> &Arc<String> {
+        self.entries
+            .iter()
+            .max_by_key(|entry| entry.len())
+            .expect("a corpus has at least one entry")
+    }
+
+    /// Creates the entry picker of one worker. Weighted selection draws from a
+    /// generator seeded with `seed` and the worker index, so runs are reproducible.
+    pub fn picker(&self, selection: Selection, worker_index: usize, seed: u64) -> Picker<'_> {
+        let next = match selection {
+            Selection::RoundRobin => worker_index % self.entries.len(),
+            Selection::Sequential | Selection::Weighted => 0,
+        };
+        Picker {
+            corpus: self,
+            selection,
+            next,
+            rng: SplitMix64(seed.wrapping_add(worker_index as u64)),
+        }
+    }
+}
+
+/// Yields the indices of the entries one worker processes.
+pub struct Picker<'a> {
+    corpus: &'a Corpus,
+    selection: Selection,
+    next: usize,
+    rng: SplitMix64,
+}
+
+impl Picker<'_> {
+    pub fn next_index(&mut self) -> usize {
+        let entries = &self.corpus.entries;
+        match self.selection {
+            Selection::RoundRobin | Selection::Sequential => {
+                let index = self.next;
+                self.next = if index + 1 == entries.len() { 0 } else { index + 1 };
+                index
+            }
+            Selection::Weighted => match self.corpus.cumulative_weights.last() {
+                Some(&total) => {
+                    // 53 random bits give a uniform f64 in [0, 1)
+                    let point = (self.rng.next_u64() >> 11) as f64 / (1u64 << 53) as f64 * total;
+                    self.corpus
+                        .cumulative_weights
+                        .partition_point(|&weight| weight <= point)
+                        .min(entries.len() - 1)
+                }
+                None => self.rng.below(entries.len() as u64) as usize,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn corpus(entries: &[&str]) -> Corpus {
+        Corpus {
+            entries: entries.iter().map(|entry| Arc::new(entry.to_string())).collect(),
+            cumulative_weights: Vec::new(),
+        }
+    }
+
+    fn picks(corpus: &Corpus, selection: Selection, worker_index: usize, seed: u64, count: usize) -> Vec<usize> {
+        let mut picker = corpus.picker(selection, worker_index, seed);
+        (0..count).map(|_| picker.next_index()).collect()
+    }
+
+    /// A file or directory unique to this test, removed when dropped.
+    struct TempPath(PathBuf);
+
+    impl TempPath {
+        fn new(name: &str) -> TempPath {
+            TempPath(std::env::temp_dir().join(format!("string_repeater_corpus_{}_{}", name, std::process::id())))
+        }
+
+        fn path(&self) -> &str {
+            self.0.to_str().unwrap()
+        }
+    }
+
+    impl Drop for TempPath {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn ordered_selections_wrap_around() {
+        let corpus = corpus(&["a", "b", "c"]);
+        let cases = [
+            (Selection::RoundRobin, 0, vec![0, 1, 2, 0, 1]),
+            (Selection::RoundRobin, 1, vec![1, 2, 0, 1, 2]),
+            (Selection::RoundRobin, 4, vec![1, 2, 0, 1, 2]),
+            (Selection::Sequential, 0, vec![0, 1, 2, 0, 1]),
+            (Selection::Sequential, 2, vec![0, 1, 2, 0, 1]),
+        ];
+        for (selection, worker_index, expected) in cases {
+            assert_eq!(picks(&corpus, selection, worker_index, 0, 5), expected, "{:?} worker {}", selection, worker_index);
+        }
+        assert_eq!(picks(&Corpus::single("x".to_string()), Selection::RoundRobin, 3, 0, 3), vec![0, 0, 0]);
+    }
+
+    #[test]
+    fn weighted_selection_follows_the_weights() {
+        let mut corpus = corpus(&["a", "b", "c"]);
+        corpus.set_weights(&[3.0, 0.0, 1.0]).unwrap();
+        let draws = picks(&corpus, Selection::Weighted, 0, 7, 40_000);
+        let mut counts = [0usize; 3];
+        for index in &draws {
+            counts[*index] += 1;
+        }
+        assert_eq!(counts[1], 0);
+        let share = counts[0] as f64 / draws.len() as f64;
+        assert!((share - 0.75).abs() < 0.02, "{:?}", counts);
+    }
+
+    #[test]
+    fn weighted_selection_is_reproducible_per_seed() {
+        let mut corpus = corpus(&["a", "b", "c", "d"]);
+        let unweighted = picks(&corpus, Selection::Weighted, 0, 42, 64);
+        assert_eq!(unweighted, picks(&corpus, Selection::Weighted, 0, 42, 64));
+        assert_ne!(unweighted, picks(&corpus, Selection::Weighted, 0, 43, 64));
+        assert_ne!(unweighted, picks(&corpus, Selection::Weighted, 1, 42, 64));
+
+        corpus.set_weights(&[1.0, 2.0, 3.0, 4.0]).unwrap();
+        let weighted = picks(&corpus, Selection::Weighted, 0, 42, 64);
+        assert_eq!(weighted, picks(&corpus, Selection::Weighted, 0, 42, 64));
+        assert_ne!(weighted, picks(&corpus, Selection::Weighted, 0, 43, 64));
+    }
+
+    #[test]
+    fn rejects_invalid_weights() {
+        let mut corpus = corpus(&["a", "b"]);
+        let cases: [&[f64]; 4] = [&[1.0], &[1.0, 2.0, 3.0], &[0.0, 0.0], &[]];
+        for weights in cases {
+            assert!(corpus.set_weights(weights).is_err(), "{:?}", weights);
+        }
+        assert!(corpus.set_weights(&[0.0, 1.0]).is_ok());
+    }
+
+    #[test]
+    fn loads_lines_of_a_file() {
+        let file = TempPath::new("file");
+        fs::write(&file.0, "first\n\nsecond line\r\n\nthird").unwrap();
+        let corpus = Corpus::load(file.path()).unwrap();
+        let entries: Vec<&str> = corpus.entries().iter().map(|entry| entry.as_str()).collect();
+        assert_eq!(entries, ["first", "second line", "third"]);
+        assert_eq!(corpus.total_bytes(), 21);
+        assert_eq!(corpus.largest().as_str(), "second line");
+
+        fs::write(&file.0, "\n\n").unwrap();
+        assert!(Corpus::load(file.path()).is_err());
+    }
+
+    #[test]
+    fn loads_files_of_a_directory_in_name_order() {
+        let dir = TempPath::new("dir");
+        fs::create_dir(&dir.0).unwrap();
+        fs::write(dir.0.join("b.txt"), "second\nfile").unwrap();
+        fs::write(dir.0.join("a.txt"), "first").unwrap();
+        fs::write(dir.0.join("c.txt"), "").unwrap();
+        fs::create_dir(dir.0.join("nested")).unwrap();
+        let corpus = Corpus::load(dir.path()).unwrap();
+        let entries: Vec<&str> = corpus.entries().iter().map(|entry| entry.as_str()).collect();
+        assert_eq!(entries, ["first", "second\nfile"]);
+
+        assert!(Corpus::load(dir.0.join("missing").to_str().unwrap()).is_err());
+    }
+}
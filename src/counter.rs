@@ -0,0 +1,163 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// How workers record completed iterations.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CounterMode {
+    /// One cache-padded counter per worker, summed when sampled.
+    Sharded,
+    /// A single counter shared by all workers (contended; kept for comparison).
+    Shared,
+}
+
+impl CounterMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            CounterMode::Sharded => "sharded",
+            CounterMode::Shared => "shared",
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<CounterMode, String> {
+        match text.trim() {
+            "sharded" => Ok(CounterMode::Sharded),
+            "shared" => Ok(CounterMode::Shared),
+            _ => Err(format!("unknown counter mode \"{}\" (expected sharded or shared)", text)),
+        }
+    }
+}
+
+/// A counter aligned to its own cache line (128 bytes covers adjacent-line prefetch).
+#[repr(align(128))]
+#[derive(Default)]
+struct Padded(AtomicUsize);
+
+/// The counters one worker publishes to.
+pub struct Slot<'a> {
+    iterations: &'a AtomicUsize,
+    /// The worker's own byte counter, or `None` when bytes follow from the iteration count.
+    bytes: Option<&'a AtomicUsize>,
+}
+
+impl Slot<'_> {
+    /// Publishes completed iterations and the bytes they touched.
+    pub fn add(&self, iterations: usize, bytes: usize) {
+        self.iterations.fetch_add(iterations, Ordering::Relaxed);
+        if let Some(counter) = self.bytes {
+            counter.fetch_add(bytes, Ordering::Relaxed);
+        }
+    }
+
+    /// Whether `add` records bytes; if not, they are derived from the iterations.
+    pub fn counts_bytes(&self) -> bool {
+        self.bytes.is_some()
+    }
+}
+
+/// Iteration and byte counters for all workers of a run.
+pub struct Counters {
+    mode: CounterMode,
+    /// One counter per worker, or a single shared one.
+    iterations: Box<[Padded]>,
+    /// One byte counter per worker in either mode, so counting bytes never adds
+    /// contention; empty when every iteration touches the same number of bytes.
+    bytes: Box<[Padded]>,
+    /// Bytes touched by every iteration, when that is a constant.
+    bytes_per_iteration: Option<usize>,
+}
+
+impl Counters {
+    /// Counters for `workers` workers. With `bytes_per_iteration`, bytes are derived
+    /// from the iteration count instead of being counted.
+    pub fn new(mode: CounterMode, workers: usize, bytes_per_iteration: Option<usize>) -> Self {
+        let counter_count = match mode {
+            CounterMode::Sharded => workers.max(1),
+            CounterMode::Shared => 1,
+        };
+        let byte_counter_count = match bytes_per_iteration {
+            Some(_) => 0,
+            None => workers.max(1),
+        };
+        Counters {
+            mode,
+            iterations: (0..counter_count).map(|_| Padded::default()).collect(),
+            bytes: (0..byte_counter_count).map(|_| Padded::default()).collect(),
+            bytes_per_iteration,
+        }
+    }
+
+    /// The counters a given worker should add to.
+    pub fn slot(&self, worker_index: usize) -> Slot<'_> {
+        let iterations = match self.mode {
+            CounterMode::Sharded => &self.iterations[worker_index].0,
+            CounterMode::Shared => &self.iterations[0].0,
+        };
+        Slot {
+            iterations,
+            bytes: self.bytes.get(worker_index).map(|counter| &counter.0),
+        }
+    }
+
+    /// Iterations of all workers.
+    pub fn total(&self) -> usize {
+        self.iterations
+            .iter()
+            .map(|counter| counter.0.load(Ordering::Relaxed))
+            .sum()
+    }
+
+    /// Bytes touched by all workers, given an iteration total read from these counters.
+    /// The total is only used when bytes are derived from it, so both stay consistent.
+    pub fn total_bytes(&self, total: usize) -> usize {
+        match self.bytes_per_iteration {
+            Some(bytes) => total.saturating_mul(bytes),
+            None => self
+                .bytes
+                .iter()
+                .map(|counter| counter.0.load(Ordering::Relaxed))
+                .sum(),
+        }
+    }
+
+    /// Per-worker totals, or `None` when all workers share one counter.
+    pub fn per_worker(&self) -> Option<Vec<usize>> {
+        match self.mode {
+            CounterMode::Sharded => Some(
+                self.iterations
+                    .iter()
+                    .map(|counter| counter.0.load(Ordering::Relaxed))
+                    .collect(),
+            ),
+            CounterMode::Shared => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn derives_bytes_from_iterations_for_a_fixed_size() {
+        let counters = Counters::new(CounterMode::Shared, 2, Some(5));
+        assert!(!counters.slot(0).counts_bytes());
+        counters.slot(0).add(3, 0);
+        counters.slot(1).add(4, 0);
+        assert_eq!(counters.total(), 7);
+        assert_eq!(counters.total_bytes(counters.total()), 35);
+        assert_eq!(counters.per_worker(), None);
+    }
+
+    #[test]
+    fn counts_bytes_per_worker_in_both_modes() {
+        for mode in [CounterMode::Sharded, CounterMode::Shared] {
+            let counters = Counters::new(mode, 2, None);
+            counters.slot(0).add(1, 10);
+            counters.slot(1).add(2, 20);
+            assert_eq!(counters.total(), 3);
+            assert_eq!(counters.total_bytes(counters.total()), 30);
+        }
+        let counters = Counters::new(CounterMode::Sharded, 2, None);
+        counters.slot(1).add(2, 20);
+        assert_eq!(counters.per_worker(), Some(vec![0, 2]));
+    }
+}
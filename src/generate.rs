@@ -0,0 +1,191 @@
+/// Seed used for generated inputs when `--seed` is not given, so runs are reproducible.
+pub const DEFAULT_SEED: u64 = 42;
+
+/// The characters a generated input is drawn from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Charset {
+    /// Printable ASCII, U+0020 to U+007E.
+    Ascii,
+    /// ASCII letters and digits.
+    Alnum,
+    /// Any Unicode scalar value, with 1- to 4-byte encodings equally likely.
+    Utf8,
+    /// CJK Unified Ideographs, 3 bytes each.
+    Cjk,
+    /// Emoji from the pictograph and emoticon blocks, 4 bytes each.
+    Emoji,
+    /// ASCII, Latin/Greek/Cyrillic, CJK and emoji, each equally likely.
+    Mixed,
+}
+
+/// Every charset, in the order they are listed in `--help`.
+pub const CHARSETS: [Charset; 6] = [
+    Charset::Ascii,
+    Charset::Alnum,
+    Charset::Utf8,
+    Charset::Cjk,
+    Charset::Emoji,
+    Charset::Mixed,
+];
+
+impl Charset {
+    pub fn name(self) -> &'static str {
+        match self {
+            Charset::Ascii => "ascii",
+            Charset::Alnum => "alnum",
+            Charset::Utf8 => "utf8",
+            Charset::Cjk => "cjk",
+            Charset::Emoji => "emoji",
+            Charset::Mixed => "mixed",
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Charset, String> {
+        let text = text.trim();
+        CHARSETS
+            .into_iter()
+            .find(|charset| charset.name() == text)
+            .ok_or_else(|| {
+                let names: Vec<&str> = CHARSETS.iter().map(|charset| charset.name()).collect();
+                format!("unknown charset \"{}\" (expected {})", text, names.join(", "))
+            })
+    }
+
+    /// Draws one character of this charset.
+    fn pick(self, rng: &mut SplitMix64) -> char {
+        const ALNUM: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+        match self {
+            Charset::Ascii => from_range(rng, 0x20, 0x7E),
+            Charset::Alnum => char::from(ALNUM[rng.below(ALNUM.len() as u64) as usize]),
+            Charset::Utf8 => match rng.below(4) {
+                0 => from_range(rng, 0x00, 0x7F),
+                1 => from_range(rng, 0x80, 0x7FF),
+                // Skip the surrogate range, which is not valid in UTF-8
+                2 => {
+                    let code = 0x800 + rng.below(0xF000) as u32;
+                    let code = if code >= 0xD800 { code + 0x800 } else { code };
+                    char::from_u32(code).expect("valid scalar")
+                }
+                _ => from_range(rng, 0x10000, 0x10FFFF),
+            },
+            Charset::Cjk => from_range(rng, 0x4E00, 0x9FFF),
+            Charset::Emoji => match rng.below(2) {
+                0 => from_range(rng, 0x1F300, 0x1F5FF),
+                _ => from_range(rng, 0x1F600, 0x1F64F),
+            },
+            Charset::Mixed => match rng.below(4) {
+                0 => Charset::Ascii.pick(rng),
+                1 => from_range(rng, 0xC0, 0x4FF),
+                2 => Charset::Cjk.pick(rng),
+                _ => Charset::Emoji.pick(rng),
+            },
+        }
+    }
+}
+
+/// Generates a string of exactly `size` bytes from `charset`.
+/// When a multi-byte character no longer fits, the remaining bytes are filled with ASCII.
+pub fn generate(size: usize, charset: Charset, seed: u64) -> String {
+    let mut rng = SplitMix64(seed);
+    let mut text = String::with_capacity(size);
+    while text.len() < size {
+        let c = charset.pick(&mut rng);
+        if text.len() + c.len_utf8() <= size {
+            text.push(c);
+        } else {
+            text.push(Charset::Ascii.pick(&mut rng));
+        }
+    }
+    text
+}
+
+/// A character drawn uniformly from an inclusive code point range without surrogates.
+fn from_range(rng: &mut SplitMix64, first: u32, last: u32) -> char {
+    let code = first + rng.below(u64::from(last - first) + 1) as u32;
+    char::from_u32(code).expect("range contains only valid scalars")
+}
+
+/// The SplitMix64 generator: small, fast and good enough for test data.
+pub struct SplitMix64(pub u64);
+
+impl SplitMix64 {
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A value in `0..bound`; the modulo bias is negligible for small bounds.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Whether `c` can be drawn from `charset`.
+    fn in_charset(charset: Charset, c: char) -> bool {
+        let code = u32::from(c);
+        let ascii = (0x20..=0x7E).contains(&code);
+        let cjk = (0x4E00..=0x9FFF).contains(&code);
+        let emoji = (0x1F300..=0x1F64F).contains(&code);
+        match charset {
+            Charset::Ascii => ascii,
+            Charset::Alnum => c.is_ascii_alphanumeric(),
+            Charset::Utf8 => true,
+            Charset::Cjk => cjk,
+            Charset::Emoji => emoji,
+            Charset::Mixed => ascii || (0xC0..=0x4FF).contains(&code) || cjk || emoji,
+        }
+    }
+
+    #[test]
+    fn generates_exactly_the_requested_size() {
+        for charset in CHARSETS {
+            for size in (0..=17).chain([63, 64, 1000, 4097]) {
+                let text = generate(size, charset, DEFAULT_SEED);
+                assert_eq!(text.len(), size, "{} at {} bytes", charset.name(), size);
+            }
+        }
+    }
+
+    #[test]
+    fn draws_only_from_the_charset() {
+        for charset in CHARSETS {
+            let size = 4099;
+            let text = generate(size, charset, 7);
+            for (position, c) in text.char_indices() {
+                // Printable ASCII fills the tail when a wide character no longer fits
+                let fill = size - position < 4 && (' '..='~').contains(&c);
+                assert!(in_charset(charset, c) || fill, "{} drew {:?} at {}", charset.name(), c, position);
+            }
+        }
+        assert!(generate(4096, Charset::Alnum, 7).chars().any(|c| c.is_ascii_digit()));
+    }
+
+    #[test]
+    fn utf8_draws_every_encoded_width_around_the_surrogates() {
+        let text = generate(1 << 16, Charset::Utf8, 3);
+        let mut widths = [0usize; 5];
+        for c in text.chars() {
+            widths[c.len_utf8()] += 1;
+        }
+        assert!(widths[1..].iter().all(|&count| count > 0), "{:?}", widths);
+        let three_byte: Vec<u32> = text.chars().filter(|c| c.len_utf8() == 3).map(u32::from).collect();
+        assert!(three_byte.iter().any(|&code| code < 0xD800));
+        assert!(three_byte.iter().any(|&code| code >= 0xE000));
+    }
+
+    #[test]
+    fn output_depends_only_on_the_seed() {
+        for charset in CHARSETS {
+            let text = generate(256, charset, 11);
+            assert_eq!(text, generate(256, charset, 11), "{}", charset.name());
+            assert_ne!(text, generate(256, charset, 12), "{}", charset.name());
+        }
+    }
+}
@@ -0,0 +1,49 @@
+//! The benchmark engine behind the `string_repeater` command.
+//!
+//! [`Benchmark`] runs a workload on one string or a corpus across worker
+//! threads and returns a [`RunResult`]:
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use string_repeater::{workload, Benchmark};
+//!
+//! let result = Benchmark::new("hello world")
+//!     .workload(workload::find("hash").unwrap())
+//!     .threads(4)
+//!     .duration(Duration::from_secs(5))
+//!     .run()
+//!     .expect("valid settings");
+//! println!("{:.2} repetitions/s", result.average_rate());
+//! ```
+
+/// Prints a progress message unless quiet mode is enabled.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! status {
+    ($quiet:expr, $($arg:tt)*) => {
+        if !$quiet {
+            println!($($arg)*);
+        }
+    };
+}
+
+pub mod affinity;
+pub mod baseline;
+pub mod benchmark;
+pub mod corpus;
+pub mod counter;
+pub mod generate;
+pub mod json;
+pub mod metrics;
+pub mod report;
+pub mod runner;
+pub mod scaling;
+pub mod sink;
+pub mod stats;
+pub mod sweep;
+pub mod workload;
+
+pub use benchmark::Benchmark;
+pub use runner::{RunResult, RunSpec};
+pub use sink::Observer;
+pub use workload::{Workload, WorkloadInfo};
@@ -1,120 +1,234 @@
+mod cli;
+mod config_file;
+
 use std::{
-    fs::{File, OpenOptions},
-    io::{self, BufRead, Seek, SeekFrom, Write},
+    fs,
+    io::{self, BufRead, IsTerminal, Read, Write},
+    net::TcpListener,
     sync::{
-        atomic::{AtomicBool, AtomicUsize, Ordering},
-        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+        Arc,
     },
-    thread::{self, JoinHandle},
-    time::{Duration, Instant},
+    thread,
+    time::{Duration, SystemTime},
 };
 
-const LOG_FILE_PATH: &str = "stats.log";
-const LOG_UPDATE_INTERVAL_MS: u64 = 1000; // Update log every 1000ms (1 second)
-
-/// The worker task that repeatedly processes the user's string.
-/// This will run in multiple threads.
-fn processor_task(
-    shared_string: Arc<String>,
-    counter: Arc<AtomicUsize>,
-    running: Arc<AtomicBool>,
-) {
-    while running.load(Ordering::Relaxed) {
-        // Clone the string (computationally cheap) and discard immediately
-        let _ = shared_string.clone();
-        counter.fetch_add(1, Ordering::Relaxed);
-    }
-}
+use cli::{Command, Config};
+use string_repeater::corpus::Corpus;
+use string_repeater::counter::CounterMode;
+use string_repeater::report::{self, RunSetup};
+use string_repeater::sink::{self, CsvSink, Formatter, Observer, RunInfo, SeriesSink, SnapshotSink};
+use string_repeater::sweep::{self, SweepPlan};
+use string_repeater::{baseline, generate, json, stats, status, Benchmark, RunSpec};
 
-/// The main task for periodically logging statistics.
-fn logger_task(
-    counter: Arc<AtomicUsize>,
-    start_time: Instant,
-    log_file_mutex: Arc<Mutex<File>>,
-    running: Arc<AtomicBool>,
-    update_interval: Duration,
-) {
-    println!(
-        "Logger thread started. Updating {} every {:?}.",
-        LOG_FILE_PATH, update_interval
-    );
+/// Exit status when the run is slower than the baseline.
+const EXIT_REGRESSION: i32 = 3;
+/// Exit status when the baseline shares no metric with the run, so nothing was checked.
+const EXIT_NO_COMPARISON: i32 = 4;
 
-    let check_interval = Duration::from_millis(100);
-    let mut last_log_time = Instant::now();
+/// Where the human-readable summary goes: stderr when stdout is reserved for the
+/// `--report-stdout` report, so that stdout holds nothing but the JSON document.
+fn summary_output(config: &Config) -> Box<dyn Write> {
+    if config.report_stdout {
+        Box::new(io::stderr())
+    } else {
+        Box::new(io::stdout())
+    }
+}
 
-    while running.load(Ordering::Relaxed) {
-        if !running.load(Ordering::Relaxed) {
-            break;
-        }
+/// Prints how the run compares to the baseline report and returns the exit status
+/// if the check failed.
+fn print_baseline_comparison(
+    out: &mut dyn Write,
+    baseline_report: &json::Value,
+    report: &json::Value,
+    tolerance: f64,
+) -> io::Result<Option<i32>> {
+    writeln!(out, "\n--- Baseline Comparison (tolerance {:.1}%) ---", tolerance * 100.0)?;
+    for mismatch in baseline::mismatches(baseline_report, report) {
+        writeln!(out, "Warning: {}", mismatch)?;
+    }
+    let comparisons = baseline::compare(baseline_report, report, tolerance);
+    for comparison in &comparisons {
+        writeln!(
+            out,
+            "{:<22} baseline {:.2} | current {:.2} | change {:+.2}% [{}]",
+            comparison.metric,
+            comparison.baseline,
+            comparison.current,
+            comparison.change() * 100.0,
+            if comparison.regressed { "REGRESSION" } else { "ok" }
+        )?;
+    }
+    if comparisons.is_empty() {
+        writeln!(out, "Verdict: NO DATA (no comparable metrics)")?;
+        Ok(Some(EXIT_NO_COMPARISON))
+    } else if comparisons.iter().any(|comparison| comparison.regressed) {
+        writeln!(out, "Verdict: REGRESSION")?;
+        Ok(Some(EXIT_REGRESSION))
+    } else {
+        writeln!(out, "Verdict: PASS")?;
+        Ok(None)
+    }
+}
 
-        if last_log_time.elapsed() >= update_interval {
-            let processed_count = counter.load(Ordering::Relaxed);
-            let elapsed_time = start_time.elapsed();
-            let elapsed_seconds = elapsed_time.as_secs_f64();
+/// Installs the Ctrl+C handler and returns the flag it sets.
+fn install_ctrlc_handler() -> Arc<AtomicBool> {
+    let interrupted = Arc::new(AtomicBool::new(false));
+    let interrupted_ctrlc = Arc::clone(&interrupted);
+    ctrlc::set_handler(move || {
+        eprintln!("\nCtrl+C received. Shutting down gracefully...");
+        interrupted_ctrlc.store(true, Ordering::Relaxed);
+    })
+    .expect("Error setting Ctrl-C handler");
+    interrupted
+}
 
-            let average_speed = if elapsed_seconds > 0.0 {
-                processed_count as f64 / elapsed_seconds
-            } else {
-                0.0
-            };
+/// Runs the matrix of thread counts and generated input sizes requested with `--sweep`.
+fn run_sweep(config: &Config) -> io::Result<()> {
+    // Progress lines would interleave with the report on stdout
+    let quiet = config.quiet || config.report_stdout;
+    if config.input.is_some()
+        || config.input_file.is_some()
+        || config.corpus_path.is_some()
+        || config.size.is_some()
+    {
+        eprintln!("Warning: STRING, --input-file, --corpus and --size are ignored by --sweep; inputs are generated per cell.");
+    }
 
-            // Format with precise float formatting
-            let stats_string = format!(
-                "Processed: {:<15} | Elapsed: {:.2}s | Speed: {:.2}/s",
-                processed_count,
-                elapsed_seconds,
-                average_speed
-            );
+    let pinned_cpus = config.pin.cpus()?;
+    let max_threads = match (config.threads, &pinned_cpus) {
+        (Some(threads), _) => threads,
+        (None, Some(cpus)) => cpus.len(),
+        (None, None) => thread::available_parallelism()?.get(),
+    };
+    let threads = config
+        .sweep_threads
+        .clone()
+        .unwrap_or_else(|| sweep::default_threads(max_threads));
+    let duration = match (config.duration, config.iterations) {
+        (None, None) => Some(sweep::DEFAULT_CELL_DURATION),
+        (duration, _) => duration,
+    };
+    // Sample at least ten times per cell so the median interval rate is meaningful
+    let interval = match duration {
+        Some(duration) => config.interval.min(duration / 10).max(Duration::from_millis(1)),
+        None => config.interval,
+    };
+    let plan = SweepPlan {
+        base: RunSpec {
+            corpus: Arc::new(Corpus::single(String::new())),
+            selection: config.selection,
+            seed: config.seed,
+            workload: config.workload,
+            threads: 1,
+            pinned_cpus,
+            counter_mode: config.counter_mode,
+            batch_size: config.batch_size,
+            duration,
+            iterations: config.iterations,
+            // An explicit zero turns the default cell warmup off
+            warmup: config
+                .warmup
+                .map_or(Some(sweep::DEFAULT_CELL_WARMUP), |warmup| Some(warmup).filter(|warmup| !warmup.is_zero())),
+            interval,
+        },
+        threads,
+        sizes: config.sweep_sizes.clone(),
+        charset: config.charset,
+        seed: config.seed,
+    };
 
-            // Truncate to exact 100 characters (prevents file resizing)
-            let padded_stats_string = stats_string.chars().take(100).collect::<String>();
+    status!(
+        quiet,
+        "Sweeping {} thread counts {:?} x {} input sizes with the {} workload.",
+        plan.threads.len(),
+        plan.threads,
+        plan.sizes.len(),
+        config.workload.name
+    );
+    status!(
+        quiet,
+        "Inputs are generated {} strings (seed {}).",
+        plan.charset.name(),
+        plan.seed
+    );
+    if let Some(warmup) = plan.base.warmup {
+        status!(quiet, "Each cell warms up for {:?}.", warmup);
+    }
+    if let Some(duration) = plan.base.duration {
+        status!(quiet, "Each cell runs for at most {:?}.", duration);
+    }
+    if let Some(iterations) = plan.base.iterations {
+        status!(quiet, "Each cell runs for at most {} iterations.", iterations);
+    }
+    status!(quiet, "Press Ctrl+C to stop.");
 
-            // Write to log file (truncates file to 100 chars)
-            {
-                let mut log_file = log_file_mutex.lock().expect("Failed to lock log file");
-                if log_file.seek(SeekFrom::Start(0)).is_ok() {
-                    log_file.write_all(padded_stats_string.as_bytes()).expect("Failed to write to log");
-                    log_file.flush().expect("Failed to flush log");
-                }
-            }
+    let interrupted = install_ctrlc_handler();
+    let run_id = sink::new_run_id();
+    let started_at = SystemTime::now();
+    let cells = sweep::run(&plan, &run_id, &interrupted, quiet)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    if quiet {
+        for warning in cells.iter().flat_map(|cell| &cell.result.warnings) {
+            eprintln!("Warning: {}", warning);
+        }
+    }
 
-            // Print to console (same content as log file - no newlines)
-            print!("{}", padded_stats_string);
+    let mut out = summary_output(config);
+    writeln!(out, "\n--- Sweep Finished ---")?;
+    let total_cells = plan.threads.len() * plan.sizes.len();
+    if cells.len() < total_cells {
+        writeln!(out, "Interrupted after {} of {} cells.", cells.len(), total_cells)?;
+    }
+    writeln!(out, "Average repetitions/s by input size and thread count:")?;
+    write!(out, "{}", sweep::table(&plan, &cells))?;
+    writeln!(out, "\nSpeedup and efficiency relative to 1 thread:")?;
+    write!(out, "{}", sweep::scaling_table(&plan, &cells))?;
 
-            last_log_time = Instant::now();
+    if config.report_path.is_some() || config.report_stdout {
+        let sweep_report = sweep::report(&plan, &run_id, started_at, &cells);
+        if let Some(report_path) = &config.report_path {
+            report::write(&sweep_report, report_path)?;
+            writeln!(out, "Report saved to: {}", report_path)?;
+        }
+        if config.report_stdout {
+            println!("{}", sweep_report);
         }
-
-        thread::sleep(check_interval);
     }
-    println!("Logger thread stopping.");
+    Ok(())
 }
 
-fn main() -> std::io::Result<()> {
-    println!("Starting high-speed string repeater program...");
+/// Reads a whole file, or stdin for `-`, keeping its whitespace and newlines exactly.
+fn read_input(path: &str) -> io::Result<String> {
+    if path == "-" {
+        let mut input = String::new();
+        io::stdin().read_to_string(&mut input)?;
+        Ok(input)
+    } else {
+        fs::read_to_string(path)
+    }
+}
 
-    // --- Get User Input String ---
-    let user_string: String;
+/// Reads the target string interactively, prompting on `out`, returning `None` on EOF.
+fn prompt_for_string(out: &mut dyn Write) -> io::Result<Option<String>> {
     loop {
-        print!("Enter the string to repeat: ");
-        io::stdout().flush()?; // Ensure prompt is displayed
+        write!(out, "Enter the string to repeat: ")?;
+        out.flush()?; // Ensure prompt is displayed
 
         let mut buffer = String::new();
         let stdin = io::stdin();
         let mut handle = stdin.lock();
-        
+
         match handle.read_line(&mut buffer) {
-            Ok(0) => {
-                println!("\nEOF detected. Exiting.");
-                return Ok(()); // Exit if no input given
-            }
+            Ok(0) => return Ok(None),
             Ok(_) => {
                 let trimmed = buffer.trim();
                 if trimmed.is_empty() {
-                    println!("Input cannot be empty. Please try again.");
+                    writeln!(out, "Input cannot be empty. Please try again.")?;
                     continue;
                 }
-                user_string = trimmed.to_string();
-                break;
+                return Ok(Some(trimmed.to_string()));
             }
             Err(e) => {
                 eprintln!("\nError reading input: {}", e);
@@ -122,98 +236,345 @@ fn main() -> std::io::Result<()> {
             }
         }
     }
-    println!("Repeating the string: \"{}\"", user_string);
-    // --- End Get User Input String ---
+}
 
-    // Wrap the user's string in an Arc for safe sharing
-    let shared_user_string = Arc::new(user_string);
+fn main() -> std::io::Result<()> {
+    let config = match cli::parse_args(std::env::args().skip(1), std::env::vars_os()) {
+        Ok(Command::Run(config)) => config,
+        Ok(Command::Help) => {
+            print!("{}", cli::usage());
+            return Ok(());
+        }
+        Ok(Command::Version) => {
+            println!("string_repeater {}", env!("CARGO_PKG_VERSION"));
+            return Ok(());
+        }
+        Ok(Command::ListModes) => {
+            print!("{}", cli::mode_list());
+            return Ok(());
+        }
+        Err(message) => {
+            eprintln!("Error: {}", message);
+            eprintln!("Run with --help for usage.");
+            std::process::exit(2);
+        }
+    };
+    if config.sweep {
+        return run_sweep(&config);
+    }
+    // Progress lines would interleave with the report on stdout
+    let quiet = config.quiet || config.report_stdout;
+    let mut out = summary_output(&config);
+
+    // Load the baseline up front so a bad path fails before the run
+    let baseline_report = match &config.baseline_path {
+        Some(path) => match baseline::load(path) {
+            Ok(report) => Some(report),
+            Err(message) => {
+                eprintln!("Error: --baseline: {}", message);
+                std::process::exit(2);
+            }
+        },
+        None => None,
+    };
+
+    status!(quiet, "Starting high-speed string repeater program...");
+
+    // --- Get User Input String ---
+    let corpus = if let Some(path) = &config.corpus_path {
+        let mut corpus = Corpus::load(path).unwrap_or_else(|e| {
+            eprintln!("Error: --corpus: {}: {}", path, e);
+            std::process::exit(2);
+        });
+        if let Some(weights) = &config.weights {
+            if let Err(message) = corpus.set_weights(weights) {
+                eprintln!("Error: --weights: {}", message);
+                std::process::exit(2);
+            }
+        }
+        status!(
+            quiet,
+            "Loaded {} corpus entries ({} bytes) from {}, selected {}.",
+            corpus.entry_count(),
+            corpus.total_bytes(),
+            path,
+            config.selection.name()
+        );
+        corpus
+    } else if let Some(size) = config.size {
+        status!(
+            quiet,
+            "Generating a {}-byte {} string (seed {}).",
+            size,
+            config.charset.name(),
+            config.seed
+        );
+        Corpus::single(generate::generate(size, config.charset, config.seed))
+    } else {
+        let input = if let Some(input) = config.input.clone() {
+            status!(quiet, "Repeating the string: \"{}\"", input);
+            input
+        } else if let Some(path) = config
+            .input_file
+            .as_deref()
+            .or_else(|| (!io::stdin().is_terminal()).then_some("-"))
+        {
+            let source = if path == "-" { "stdin" } else { path };
+            let input = read_input(path).unwrap_or_else(|e| {
+                eprintln!("Error: failed to read the input from {}: {}", source, e);
+                std::process::exit(2);
+            });
+            status!(quiet, "Read {} bytes from {}.", input.len(), source);
+            input
+        } else {
+            match prompt_for_string(&mut out)? {
+                Some(input) => {
+                    status!(quiet, "Repeating the string: \"{}\"", input);
+                    input
+                }
+                None => {
+                    writeln!(out, "\nEOF detected. Exiting.")?;
+                    return Ok(()); // Exit if no input given
+                }
+            }
+        };
+        let input = if config.trim {
+            input.trim().to_string()
+        } else {
+            input
+        };
+        if input.is_empty() {
+            eprintln!("Error: the string to repeat cannot be empty.");
+            std::process::exit(2);
+        }
+        Corpus::single(input)
+    };
+    status!(quiet, "Workload: {}", config.workload.name);
+    // --- End Get User Input String ---
 
-    // Determine number of worker threads
-    let num_worker_threads = thread::available_parallelism()?.get();
-    println!(
+    // Determine worker CPU placement and number of worker threads
+    let pinned_cpus = config.pin.cpus()?;
+    let num_worker_threads = match (config.threads, &pinned_cpus) {
+        (Some(threads), _) => threads,
+        (None, Some(cpus)) => cpus.len(),
+        (None, None) => thread::available_parallelism()?.get(),
+    };
+    status!(
+        quiet,
         "Will spawn {} worker threads to repeat the string.",
         num_worker_threads
     );
-    println!("Statistics logged to {} every {}ms.", LOG_FILE_PATH, LOG_UPDATE_INTERVAL_MS);
-    println!("Press Ctrl+C to stop.");
-
-    // Shared state: counter and running flag
-    let processed_counter = Arc::new(AtomicUsize::new(0));
-    let running_flag = Arc::new(AtomicBool::new(true));
-
-    // Record start time (after getting user input)
-    let start_time = Instant::now();
+    if let Some(cpus) = &pinned_cpus {
+        status!(quiet, "Pinning workers to CPUs {:?}.", cpus);
+        if num_worker_threads > cpus.len() {
+            eprintln!(
+                "Warning: {} workers share {} pinned CPUs.",
+                num_worker_threads,
+                cpus.len()
+            );
+        }
+    }
+    if config.snapshot {
+        status!(
+            quiet,
+            "Statistics logged to {} every {:?}.",
+            config.log_path, config.interval
+        );
+    }
+    if let Some(csv_path) = &config.csv_path {
+        status!(quiet, "Samples written as CSV to {}.", csv_path);
+    }
+    if let Some(series_path) = &config.series_path {
+        status!(
+            quiet,
+            "Statistics appended to {} every {:?}.",
+            series_path, config.interval
+        );
+    }
+    status!(
+        quiet,
+        "Counting with {} counters, publishing every {} iterations.",
+        config.counter_mode.name(),
+        config.batch_size
+    );
+    if let Some(duration) = config.duration {
+        status!(quiet, "Running for at most {:?}.", duration);
+    }
+    if let Some(iterations) = config.iterations {
+        status!(quiet, "Running for at most {} iterations.", iterations);
+    }
+    let warmup = config.warmup.filter(|warmup| !warmup.is_zero());
+    if let Some(warmup) = warmup {
+        status!(quiet, "Warming up for {:?} before measuring.", warmup);
+    }
+    status!(quiet, "Press Ctrl+C to stop.");
 
     // Log File Setup
-    let log_file = OpenOptions::new()
-        .write(true)
-        .create(true)
-        .truncate(true)
-        .open(LOG_FILE_PATH)?;
-    let log_file_mutex = Arc::new(Mutex::new(log_file));
-
-    // --- Spawn Threads ---
-    let mut thread_handles: Vec<JoinHandle<()>> = Vec::with_capacity(num_worker_threads + 1);
-
-    // Spawn Worker Threads
-    println!("Spawning worker threads...");
-    for _ in 0..num_worker_threads {
-        let processor_string_clone = Arc::clone(&shared_user_string);
-        let processor_counter_clone = Arc::clone(&processed_counter);
-        let processor_running_clone = Arc::clone(&running_flag);
-        
-        let handle = thread::spawn(move || {
-            processor_task(processor_string_clone, processor_counter_clone, processor_running_clone);
-        });
-        thread_handles.push(handle);
-    }
-    println!("All worker threads spawned.");
-
-    // Spawn Logger Thread
-    let logger_counter_clone = Arc::clone(&processed_counter);
-    let logger_file_clone = Arc::clone(&log_file_mutex);
-    let logger_running_clone = Arc::clone(&running_flag);
-    let log_interval = Duration::from_millis(LOG_UPDATE_INTERVAL_MS);
-
-    let logger_handle = thread::spawn(move || {
-        logger_task(
-            logger_counter_clone,
-            start_time,
-            logger_file_clone,
-            logger_running_clone,
-            log_interval,
-        );
-    });
-    thread_handles.push(logger_handle);
-    // --- End Spawn Threads ---
+    let run_info = RunInfo {
+        run_id: sink::new_run_id(),
+        threads: num_worker_threads,
+        mode: config.workload.name.to_string(),
+    };
+    let formatter = Formatter {
+        format: config.format,
+        run: run_info.clone(),
+    };
+    let mut observers: Vec<Box<dyn Observer>> = Vec::new();
+    if config.snapshot {
+        // A JSON record must fit whole, or it would not parse
+        let needed = formatter.max_line_len().map_or(0, |len| len + 1);
+        let log_width = match config.log_width {
+            Some(width) if width < needed => {
+                eprintln!(
+                    "Error: --log-width: {} bytes is too small for {} records, which need up to {} bytes",
+                    width,
+                    config.format.name(),
+                    needed
+                );
+                std::process::exit(2);
+            }
+            Some(width) => width,
+            None => cli::LOG_LINE_WIDTH.max(needed),
+        };
+        observers.push(Box::new(SnapshotSink::create(
+            &config.log_path,
+            log_width,
+            formatter.clone(),
+        )?));
+    }
+    if let Some(series_path) = &config.series_path {
+        observers.push(Box::new(SeriesSink::create(series_path, formatter.clone())?));
+    }
+    if let Some(csv_path) = &config.csv_path {
+        let per_worker_columns = match config.counter_mode {
+            CounterMode::Sharded => num_worker_threads,
+            CounterMode::Shared => 0,
+        };
+        observers.push(Box::new(CsvSink::create(csv_path, per_worker_columns)?));
+    }
+
+    // Metrics Endpoint Setup (bound now so address errors surface before the run)
+    let metrics_listener = match config.metrics_addr {
+        Some(addr) => {
+            let listener = TcpListener::bind(addr)?;
+            status!(
+                quiet,
+                "Serving metrics at http://{}/metrics",
+                listener.local_addr()?
+            );
+            Some(listener)
+        }
+        None => None,
+    };
 
     // Graceful Shutdown Handling
-    let running_flag_ctrlc = Arc::clone(&running_flag);
-    ctrlc::set_handler(move || {
-        println!("\nCtrl+C received. Shutting down gracefully...");
-        running_flag_ctrlc.store(false, Ordering::Relaxed);
-    }).expect("Error setting Ctrl-C handler");
+    let interrupted = install_ctrlc_handler();
 
-    // Wait for all threads to finish
-    println!("Waiting for threads to complete...");
-    for handle in thread_handles {
-        handle.join().expect("A worker thread panicked");
+    let mut benchmark = Benchmark::with_corpus(corpus)
+        .workload(config.workload)
+        .threads(num_worker_threads)
+        .counter_mode(config.counter_mode)
+        .batch_size(config.batch_size)
+        .interval(config.interval)
+        .selection(config.selection, config.seed)
+        .run_id(run_info.run_id.clone())
+        .observers(observers)
+        .quiet(quiet);
+    if let Some(cpus) = pinned_cpus {
+        benchmark = benchmark.pin(cpus);
+    }
+    if let Some(duration) = config.duration {
+        benchmark = benchmark.duration(duration);
+    }
+    if let Some(iterations) = config.iterations {
+        benchmark = benchmark.iterations(iterations);
+    }
+    if let Some(warmup) = warmup {
+        benchmark = benchmark.warmup(warmup);
+    }
+    if !quiet {
+        benchmark = benchmark.console(formatter);
+    }
+    if let Some(listener) = metrics_listener {
+        benchmark = benchmark.metrics(listener);
+    }
+    let spec = benchmark.spec();
+    let result = benchmark
+        .run_until(&interrupted)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    // A quiet run did not print its warnings as they happened
+    if quiet {
+        for warning in &result.warnings {
+            eprintln!("Warning: {}", warning);
+        }
     }
 
     // Final statistics output
-    let final_count = processed_counter.load(Ordering::Relaxed);
-    let total_time = start_time.elapsed();
-    let avg_speed = if total_time.as_secs_f64() > 0.0 {
-        final_count as f64 / total_time.as_secs_f64()
-    } else {
-        0.0
-    };
+    writeln!(out, "\n--- Program Finished ---")?;
+    if warmup.is_some() {
+        writeln!(
+            out,
+            "Warmup (excluded): {} repetitions in {:?} ({:.2} repetitions/s)",
+            result.warmup_total,
+            result.warmup_elapsed,
+            stats::rate(result.warmup_total, result.warmup_elapsed)
+        )?;
+    }
+    writeln!(out, "Total repetitions processed: {}", result.total)?;
+    writeln!(out, "Total time elapsed: {:?}", result.elapsed)?;
+    writeln!(out, "Average speed: {:.2} repetitions/s", result.average_rate())?;
+    let byte_rate = result.average_byte_rate();
+    writeln!(out, "Total bytes touched: {}", result.bytes)?;
+    writeln!(
+        out,
+        "Average throughput: {:.2} bytes/s ({:.3} GiB/s)",
+        byte_rate,
+        stats::gib_per_second(byte_rate)
+    )?;
+    if let Some(summary) = result.interval_summary() {
+        writeln!(
+            out,
+            "Interval speed over {} samples: min {:.2} | max {:.2} | mean {:.2} | median {:.2} | stddev {:.2} repetitions/s",
+            summary.samples, summary.min, summary.max, summary.mean, summary.median, summary.stddev
+        )?;
+    }
+    if let Some(per_worker) = &result.per_worker {
+        writeln!(out, "Per-thread repetitions: {:?}", per_worker)?;
+    }
+    if config.snapshot {
+        writeln!(out, "Log file saved to: {}", config.log_path)?;
+    }
+    if let Some(series_path) = &config.series_path {
+        writeln!(out, "Time series saved to: {}", series_path)?;
+    }
+    if let Some(csv_path) = &config.csv_path {
+        writeln!(out, "CSV samples saved to: {}", csv_path)?;
+    }
 
-    println!("\n--- Program Finished ---");
-    println!("Total repetitions processed: {}", final_count);
-    println!("Total time elapsed: {:?}", total_time);
-    println!("Average speed: {:.2} repetitions/s", avg_speed);
-    println!("Log file saved to: {}", LOG_FILE_PATH);
+    // Machine-readable final report
+    let mut failed_check = None;
+    if config.report_path.is_some() || config.report_stdout || baseline_report.is_some() {
+        let setup = RunSetup {
+            spec: &spec,
+            run: &run_info,
+            generated: config.size.map(|_| (config.charset, config.seed)),
+            corpus: config.corpus_path.is_some(),
+        };
+        let final_report = report::build(&setup, &result);
+        if let Some(report_path) = &config.report_path {
+            report::write(&final_report, report_path)?;
+            writeln!(out, "Report saved to: {}", report_path)?;
+        }
+        if let Some(baseline_report) = &baseline_report {
+            failed_check = print_baseline_comparison(&mut out, baseline_report, &final_report, config.tolerance)?;
+        }
+        if config.report_stdout {
+            println!("{}", final_report);
+        }
+    }
 
+    if let Some(status) = failed_check {
+        std::process::exit(status);
+    }
     Ok(())
 }
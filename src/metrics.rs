@@ -0,0 +1,311 @@
+use std::{
+    fmt::Write as _,
+    io::{self, BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::counter::Counters;
+use crate::sink::{Observer, RunInfo, Warnings};
+use crate::stats::{self, Sample};
+
+/// How long the listener sleeps when no connection is pending.
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// How long a client may take to send its request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Parses a listen address, resolving a host name to its first address;
+/// a bare port binds to localhost.
+pub fn parse_addr(text: &str) -> Result<SocketAddr, String> {
+    let text = text.trim();
+    if let Ok(port) = text.parse::<u16>() {
+        return Ok(SocketAddr::from(([127, 0, 0, 1], port)));
+    }
+    text.to_socket_addrs()
+        .map_err(|e| format!("invalid address \"{}\" (expected HOST:PORT or PORT): {}", text, e))?
+        .next()
+        .ok_or_else(|| format!("address \"{}\" did not resolve", text))
+}
+
+/// The latest sample taken by the logger, shared with the HTTP listener.
+pub type LatestSample = Arc<Mutex<Option<Sample>>>;
+
+/// Publishes each sample for the metrics endpoint.
+pub struct MetricsSink {
+    latest: LatestSample,
+}
+
+impl MetricsSink {
+    pub fn new(latest: LatestSample) -> MetricsSink {
+        MetricsSink { latest }
+    }
+}
+
+impl Observer for MetricsSink {
+    fn on_sample(&mut self, sample: &Sample) -> io::Result<()> {
+        *self.latest.lock().expect("Failed to lock latest sample") = Some(sample.clone());
+        Ok(())
+    }
+}
+
+/// Everything the endpoint reports, read on every scrape.
+pub struct MetricsSource {
+    pub counters: Arc<Counters>,
+    pub start_time: Instant,
+    pub latest: LatestSample,
+    pub run: RunInfo,
+    pub warnings: Warnings,
+}
+
+/// Serves `/metrics` until the running flag is cleared.
+pub fn metrics_task(listener: TcpListener, source: MetricsSource, running: Arc<AtomicBool>) {
+    if let Err(e) = listener.set_nonblocking(true) {
+        source.warnings.warn(format!("metrics endpoint disabled: {}", e));
+        return;
+    }
+    while running.load(Ordering::Relaxed) {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                if let Err(e) = handle_connection(stream, &source) {
+                    source.warnings.warn(format!("metrics request failed: {}", e));
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => thread::sleep(ACCEPT_POLL_INTERVAL),
+            Err(e) => source.warnings.warn(format!("metrics listener error: {}", e)),
+        }
+    }
+}
+
+fn handle_connection(stream: TcpStream, source: &MetricsSource) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut reader = BufReader::new(stream);
+
+    // Only the request line matters; skip the headers
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or("");
+    let path = parts.next().unwrap_or("");
+    let (status, content_type, body) = match (method, path) {
+        ("GET", "/metrics") => (
+            "200 OK",
+            "text/plain; version=0.0.4; charset=utf-8",
+            render(source),
+        ),
+        ("GET", _) => ("404 Not Found", "text/plain", "Not found; try /metrics\n".to_string()),
+        _ => ("405 Method Not Allowed", "text/plain", "Only GET is supported\n".to_string()),
+    };
+
+    let mut stream = reader.into_inner();
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+/// Renders the current metrics in the Prometheus text exposition format.
+fn render(source: &MetricsSource) -> String {
+    let labels = format!(
+        "run_id=\"{}\",mode=\"{}\"",
+        escape_label(&source.run.run_id),
+        escape_label(&source.run.mode)
+    );
+    let count = source.counters.total();
+    let bytes = source.counters.total_bytes(count);
+    let elapsed = source.start_time.elapsed();
+    let (interval_rate, interval_byte_rate) = source
+        .latest
+        .lock()
+        .expect("Failed to lock latest sample")
+        .as_ref()
+        .map_or((0.0, 0.0), |sample| (sample.interval_rate(), sample.interval_byte_rate()));
+
+    let mut text = String::new();
+    let mut metric = |name: &str, kind: &str, help: &str, value: String| {
+        let _ = writeln!(text, "# HELP string_repeater_{} {}", name, help);
+        let _ = writeln!(text, "# TYPE string_repeater_{} {}", name, kind);
+        let _ = writeln!(text, "string_repeater_{}{{{}}} {}", name, labels, value);
+    };
+    metric(
+        "processed_total",
+        "counter",
+        "Repetitions processed since the measurement started.",
+        count.to_string(),
+    );
+    metric(
+        "elapsed_seconds",
+        "gauge",
+        "Seconds since the measurement started.",
+        format!("{:.3}", elapsed.as_secs_f64()),
+    );
+    metric(
+        "interval_rate",
+        "gauge",
+        "Repetitions per second over the latest logger interval.",
+        format!("{:.2}", interval_rate),
+    );
+    metric(
+        "average_rate",
+        "gauge",
+        "Repetitions per second since the measurement started.",
+        format!("{:.2}", stats::rate(count, elapsed)),
+    );
+    metric(
+        "processed_bytes_total",
+        "counter",
+        "Bytes touched by the workload since the measurement started.",
+        bytes.to_string(),
+    );
+    metric(
+        "interval_byte_rate",
+        "gauge",
+        "Bytes per second over the latest logger interval.",
+        format!("{:.2}", interval_byte_rate),
+    );
+    metric(
+        "average_byte_rate",
+        "gauge",
+        "Bytes per second since the measurement started.",
+        format!("{:.2}", stats::rate(bytes, elapsed)),
+    );
+    metric(
+        "threads",
+        "gauge",
+        "Number of worker threads.",
+        source.run.threads.to_string(),
+    );
+
+    if let Some(per_worker) = source.counters.per_worker() {
+        let name = "string_repeater_worker_processed_total";
+        let _ = writeln!(text, "# HELP {} Repetitions processed by each worker.", name);
+        let _ = writeln!(text, "# TYPE {} counter", name);
+        for (worker, total) in per_worker.iter().enumerate() {
+            let _ = writeln!(text, "{}{{{},thread=\"{}\"}} {}", name, labels, worker, total);
+        }
+    }
+    text
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::counter::CounterMode;
+    use std::net::{IpAddr, Ipv6Addr};
+
+    #[test]
+    fn parses_listen_addresses() {
+        let cases = [
+            ("9100", Some(SocketAddr::from(([127, 0, 0, 1], 9100)))),
+            (" 0 ", Some(SocketAddr::from(([127, 0, 0, 1], 0)))),
+            ("0.0.0.0:9100", Some(SocketAddr::from(([0, 0, 0, 0], 9100)))),
+            ("[::1]:9100", Some(SocketAddr::new(IpAddr::V6(Ipv6Addr::LOCALHOST), 9100))),
+            ("127.0.0.1", None),
+            ("127.0.0.1:70000", None),
+            ("not an address", None),
+            ("", None),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(parse_addr(text).ok(), expected, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn resolves_host_names() {
+        let addr = parse_addr("localhost:9100").unwrap();
+        assert!(addr.ip().is_loopback(), "{}", addr);
+        assert_eq!(addr.port(), 9100);
+        assert!(parse_addr("localhost").is_err());
+    }
+
+    fn source(mode: CounterMode, mode_name: &str) -> MetricsSource {
+        let counters = Counters::new(mode, 2, Some(4));
+        counters.slot(0).add(3, 0);
+        counters.slot(1).add(5, 0);
+        MetricsSource {
+            counters: Arc::new(counters),
+            start_time: Instant::now(),
+            latest: LatestSample::default(),
+            run: RunInfo {
+                run_id: "run-1".to_string(),
+                threads: 2,
+                mode: mode_name.to_string(),
+            },
+            warnings: Warnings::new(true),
+        }
+    }
+
+    #[test]
+    fn renders_every_metric_with_help_and_type() {
+        let text = render(&source(CounterMode::Sharded, "deep-clone"));
+        let labels = "{run_id=\"run-1\",mode=\"deep-clone\"}";
+        for (name, kind, value) in [
+            ("processed_total", "counter", "8"),
+            ("processed_bytes_total", "counter", "32"),
+            ("interval_rate", "gauge", "0.00"),
+            ("threads", "gauge", "2"),
+        ] {
+            let name = format!("string_repeater_{}", name);
+            assert!(text.contains(&format!("# HELP {} ", name)), "{}", name);
+            assert!(text.contains(&format!("# TYPE {} {}\n", name, kind)), "{}", name);
+            assert!(text.contains(&format!("\n{}{} {}\n", name, labels, value)), "{}", name);
+        }
+        for name in ["elapsed_seconds", "average_rate", "interval_byte_rate", "average_byte_rate"] {
+            assert!(text.contains(&format!("# TYPE string_repeater_{} gauge\n", name)), "{}", name);
+        }
+        for line in text.lines().filter(|line| !line.starts_with('#')) {
+            assert!(line.starts_with("string_repeater_"), "{}", line);
+            assert!(line.contains("{run_id=\"run-1\",mode=\"deep-clone\""), "{}", line);
+        }
+    }
+
+    #[test]
+    fn renders_per_worker_series_only_when_sharded() {
+        let name = "string_repeater_worker_processed_total";
+        let sharded = render(&source(CounterMode::Sharded, "hash"));
+        assert!(sharded.contains(&format!("# TYPE {} counter\n", name)));
+        assert!(sharded.contains(&format!("{}{{run_id=\"run-1\",mode=\"hash\",thread=\"0\"}} 3\n", name)));
+        assert!(sharded.contains(&format!("{}{{run_id=\"run-1\",mode=\"hash\",thread=\"1\"}} 5\n", name)));
+
+        let shared = render(&source(CounterMode::Shared, "hash"));
+        assert!(!shared.contains(name));
+        assert!(shared.contains("string_repeater_processed_total{run_id=\"run-1\",mode=\"hash\"} 8\n"));
+    }
+
+    #[test]
+    fn escapes_label_values() {
+        let cases = [
+            ("plain", "plain"),
+            ("a\"b", "a\\\"b"),
+            ("back\\slash", "back\\\\slash"),
+            ("two\nlines", "two\\nlines"),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(escape_label(value), expected, "{:?}", value);
+        }
+        let text = render(&source(CounterMode::Shared, "odd\"mode"));
+        assert!(text.contains("mode=\"odd\\\"mode\""));
+    }
+}
@@ -0,0 +1,115 @@
+use std::{
+    fs,
+    io::{self, Write},
+    thread,
+};
+
+use crate::generate::Charset;
+use crate::json::Value;
+use crate::runner::{RunResult, RunSpec};
+use crate::sink::{self, RunInfo};
+use crate::stats;
+
+/// Context needed to describe how a run was set up.
+pub struct RunSetup<'a> {
+    pub spec: &'a RunSpec,
+    pub run: &'a RunInfo,
+    /// Charset and seed of a generated input.
+    pub generated: Option<(Charset, u64)>,
+    /// Whether the input is a loaded corpus rather than a single string.
+    pub corpus: bool,
+}
+
+/// Builds the final report: configuration, environment and results.
+pub fn build(setup: &RunSetup, result: &RunResult) -> Value {
+    let spec = setup.spec;
+    let corpus = setup.corpus.then_some(&spec.corpus);
+    let configuration = Value::object([
+        ("mode", Value::from(spec.workload.name)),
+        ("threads", Value::from(setup.run.threads)),
+        ("pinned_cpus", Value::from(spec.pinned_cpus.clone())),
+        ("counter", Value::from(spec.counter_mode.name())),
+        ("batch", Value::from(spec.batch_size)),
+        ("duration_s", Value::from(spec.duration.map(|d| d.as_secs_f64()))),
+        ("iterations", Value::from(spec.iterations)),
+        ("warmup_s", Value::from(spec.warmup.map(|d| d.as_secs_f64()))),
+        ("interval_s", Value::from(spec.interval.as_secs_f64())),
+        ("input_bytes", Value::from(spec.corpus.total_bytes())),
+        ("input_charset", Value::from(setup.generated.map(|(charset, _)| charset.name()))),
+        ("input_seed", Value::from(setup.generated.map(|(_, seed)| seed))),
+        ("corpus_entries", Value::from(corpus.map(|corpus| corpus.entry_count()))),
+        ("selection", Value::from(corpus.map(|_| spec.selection.name()))),
+    ]);
+
+    let interval = match result.interval_summary() {
+        Some(summary) => Value::object([
+            ("samples", Value::from(summary.samples)),
+            ("min", Value::from(summary.min)),
+            ("max", Value::from(summary.max)),
+            ("mean", Value::from(summary.mean)),
+            ("median", Value::from(summary.median)),
+            ("stddev", Value::from(summary.stddev)),
+        ]),
+        None => Value::Null,
+    };
+    let warmup = match spec.warmup {
+        Some(_) => Value::object([
+            ("total", Value::from(result.warmup_total)),
+            ("elapsed_s", Value::from(result.warmup_elapsed.as_secs_f64())),
+            (
+                "average_rate",
+                Value::from(stats::rate(result.warmup_total, result.warmup_elapsed)),
+            ),
+        ]),
+        None => Value::Null,
+    };
+    let results = Value::object([
+        ("total", Value::from(result.total)),
+        ("bytes", Value::from(result.bytes)),
+        ("elapsed_s", Value::from(result.elapsed.as_secs_f64())),
+        ("average_rate", Value::from(result.average_rate())),
+        ("average_byte_rate", Value::from(result.average_byte_rate())),
+        ("interval_rate", interval),
+        ("per_thread", Value::from(result.per_worker.clone())),
+        ("warmup", warmup),
+    ]);
+
+    Value::object([
+        ("run_id", Value::from(setup.run.run_id.as_str())),
+        ("started_at", Value::from(sink::format_timestamp(result.started_at))),
+        ("config", configuration),
+        ("environment", environment()),
+        ("results", results),
+    ])
+}
+
+/// Describes the build and the machine the benchmark ran on.
+pub fn environment() -> Value {
+    Value::object([
+        ("version", Value::from(env!("CARGO_PKG_VERSION"))),
+        ("build", Value::from(if cfg!(debug_assertions) { "debug" } else { "release" })),
+        ("os", Value::from(std::env::consts::OS)),
+        ("arch", Value::from(std::env::consts::ARCH)),
+        (
+            "available_parallelism",
+            Value::from(thread::available_parallelism().ok().map(|n| n.get())),
+        ),
+        ("hostname", Value::from(hostname())),
+    ])
+}
+
+/// Writes the report to `path` as a single JSON document.
+pub fn write(report: &Value, path: &str) -> io::Result<()> {
+    let mut file = fs::File::create(path)?;
+    writeln!(file, "{}", report)?;
+    file.flush()
+}
+
+fn hostname() -> Option<String> {
+    fs::read_to_string("/proc/sys/kernel/hostname")
+        .ok()
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .or_else(|| std::env::var("COMPUTERNAME").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
@@ -0,0 +1,541 @@
+use std::{
+    net::TcpListener,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::affinity;
+use crate::corpus::{Corpus, Selection};
+use crate::counter::{CounterMode, Counters};
+use crate::metrics::{self, MetricsSink, MetricsSource};
+use crate::sink::{Observer, RunInfo, Warnings};
+use crate::stats::{self, RateSummary, Sampler};
+use crate::workload::WorkloadInfo;
+
+/// How often the main thread checks whether the run should stop.
+const SHUTDOWN_CHECK_INTERVAL: Duration = Duration::from_millis(10);
+
+/// What to run and for how long.
+#[derive(Clone)]
+pub struct RunSpec {
+    pub corpus: Arc<Corpus>,
+    pub selection: Selection,
+    /// Seed of weighted selection.
+    pub seed: u64,
+    pub workload: &'static WorkloadInfo,
+    pub threads: usize,
+    /// CPUs to pin workers to, in order, or `None` for no pinning.
+    pub pinned_cpus: Option<Vec<usize>>,
+    pub counter_mode: CounterMode,
+    pub batch_size: usize,
+    pub duration: Option<Duration>,
+    pub iterations: Option<usize>,
+    pub warmup: Option<Duration>,
+    pub interval: Duration,
+}
+
+impl RunSpec {
+    /// Checks the settings the command line validates when parsing, for specs built in code.
+    pub fn validate(&self) -> Result<(), String> {
+        // Checked first, because an empty list also leaves no worker threads
+        if let Some(cpus) = &self.pinned_cpus {
+            if cpus.is_empty() {
+                return Err("the list of CPUs to pin to is empty".to_string());
+            }
+            if let Some(cpu) = cpus.iter().find(|&&cpu| cpu > affinity::MAX_CPU) {
+                return Err(format!("CPU {} is out of range (max {})", cpu, affinity::MAX_CPU));
+            }
+        }
+        if self.threads == 0 {
+            return Err("the thread count must be at least 1".to_string());
+        }
+        if self.batch_size == 0 {
+            return Err("the batch size must be at least 1".to_string());
+        }
+        if self.interval.is_zero() {
+            return Err("the sample interval must be greater than zero".to_string());
+        }
+        Ok(())
+    }
+
+    /// Returns why the run should stop, if one of its limits has been reached.
+    fn limit_reached(&self, elapsed: Duration, processed: usize) -> Option<&'static str> {
+        if self.duration.is_some_and(|duration| elapsed >= duration) {
+            return Some("Duration");
+        }
+        if self.iterations.is_some_and(|iterations| processed >= iterations) {
+            return Some("Iteration");
+        }
+        None
+    }
+}
+
+/// Where a run reports while it is in progress.
+pub struct RunOutputs {
+    pub run: RunInfo,
+    /// Receive every sample, then the results.
+    pub observers: Vec<Box<dyn Observer>>,
+    /// Serve Prometheus metrics on this listener for the duration of the run.
+    pub metrics_listener: Option<TcpListener>,
+    /// Suppress progress messages.
+    pub quiet: bool,
+}
+
+/// Measured results of a finished run.
+pub struct RunResult {
+    pub started_at: SystemTime,
+    pub total: usize,
+    /// Bytes touched by the processed iterations.
+    pub bytes: usize,
+    pub elapsed: Duration,
+    pub interval_rates: Vec<f64>,
+    pub per_worker: Option<Vec<usize>>,
+    pub warmup_total: usize,
+    pub warmup_elapsed: Duration,
+    /// Problems the run worked around, such as a worker that could not be pinned.
+    pub warnings: Vec<String>,
+}
+
+impl RunResult {
+    /// Iterations per second over the measured phase.
+    pub fn average_rate(&self) -> f64 {
+        stats::rate(self.total, self.elapsed)
+    }
+
+    /// Bytes touched per second over the measured phase.
+    pub fn average_byte_rate(&self) -> f64 {
+        stats::rate(self.bytes, self.elapsed)
+    }
+
+    /// Summary of the interval rates, or `None` if no sample was taken.
+    pub fn interval_summary(&self) -> Option<RateSummary> {
+        RateSummary::from_rates(&self.interval_rates)
+    }
+}
+
+/// Placement and counting parameters for one worker thread.
+struct WorkerPlan {
+    index: usize,
+    cpu: Option<usize>,
+    /// Iterations counted locally before publishing them to the worker's counter.
+    batch_size: usize,
+    /// Iterations this worker performs before stopping on its own, if limited.
+    quota: Option<usize>,
+    selection: Selection,
+    seed: u64,
+    /// Bytes touched by one iteration on each corpus entry, by entry index.
+    bytes_touched: Arc<[usize]>,
+    warnings: Warnings,
+}
+
+/// The worker task that repeatedly runs the selected workload on the corpus entries.
+/// This will run in multiple threads. Returns when the worker stopped counting.
+fn processor_task(
+    corpus: Arc<Corpus>,
+    workload_info: &'static WorkloadInfo,
+    plan: WorkerPlan,
+    counters: Arc<Counters>,
+    warmup_counter: Arc<AtomicUsize>,
+    running: Arc<AtomicBool>,
+    warming: Arc<AtomicBool>,
+) -> Instant {
+    if let Some(cpu) = plan.cpu {
+        if let Err(e) = affinity::pin_current_thread(cpu) {
+            plan.warnings.warn(format!("failed to pin worker to CPU {}: {}", cpu, e));
+        }
+    }
+
+    let mut workload = (workload_info.create)();
+    workload.setup(corpus.largest());
+    let mut picker = corpus.picker(plan.selection, plan.index, plan.seed);
+    let entries = corpus.entries();
+
+    // Warm up uncounted until the main thread starts the measurement
+    let mut warmup_completed = 0;
+    while warming.load(Ordering::Relaxed) && running.load(Ordering::Relaxed) {
+        workload.iterate(&entries[picker.next_index()]);
+        warmup_completed += 1;
+    }
+    warmup_counter.fetch_add(warmup_completed, Ordering::Relaxed);
+
+    // Count locally and publish every `batch_size` iterations
+    let slot = counters.slot(plan.index);
+    let quota = plan.quota.unwrap_or(usize::MAX);
+    let mut completed = 0;
+    let mut pending = 0;
+    if slot.counts_bytes() {
+        let mut pending_bytes = 0;
+        while completed < quota && running.load(Ordering::Relaxed) {
+            let index = picker.next_index();
+            workload.iterate(&entries[index]);
+            completed += 1;
+            pending += 1;
+            pending_bytes += plan.bytes_touched[index];
+            if pending >= plan.batch_size {
+                slot.add(pending, pending_bytes);
+                pending = 0;
+                pending_bytes = 0;
+            }
+        }
+        slot.add(pending, pending_bytes);
+    } else {
+        // One input: bytes follow from the iteration count, so skip the picker as well
+        let entry = &entries[picker.next_index()];
+        while completed < quota && running.load(Ordering::Relaxed) {
+            workload.iterate(entry);
+            completed += 1;
+            pending += 1;
+            if pending >= plan.batch_size {
+                slot.add(pending, 0);
+                pending = 0;
+            }
+        }
+        slot.add(pending, 0);
+    }
+    let finished = Instant::now();
+    workload.teardown();
+    finished
+}
+
+/// The main task for periodically sampling statistics and passing them to the observers.
+/// Returns the interval rate of every sample taken, and the observers that did not fail.
+fn logger_task(
+    counters: Arc<Counters>,
+    start_time: Instant,
+    mut observers: Vec<Box<dyn Observer>>,
+    running: Arc<AtomicBool>,
+    update_interval: Duration,
+    quiet: bool,
+    warnings: Warnings,
+) -> (Vec<f64>, Vec<Box<dyn Observer>>) {
+    status!(
+        quiet,
+        "Logger thread started. Updating {} observer(s) every {:?}.",
+        observers.len(),
+        update_interval
+    );
+
+    let check_interval = update_interval.min(Duration::from_millis(100));
+    let mut last_log_time = Instant::now();
+    let mut sampler = Sampler::default();
+    let mut interval_rates = Vec::new();
+
+    while running.load(Ordering::Relaxed) {
+        if !running.load(Ordering::Relaxed) {
+            break;
+        }
+
+        if last_log_time.elapsed() >= update_interval {
+            let sample = sampler.sample(&counters, start_time.elapsed());
+            interval_rates.push(sample.interval_rate());
+
+            // An observer that fails is detached so the others keep receiving samples
+            observers.retain_mut(|observer| match observer.on_sample(&sample) {
+                Ok(()) => true,
+                Err(e) => {
+                    warnings.warn(format!("detaching an observer after it failed: {}", e));
+                    false
+                }
+            });
+
+            last_log_time = Instant::now();
+        }
+
+        thread::sleep(check_interval);
+    }
+    status!(quiet, "Logger thread stopping.");
+    (interval_rates, observers)
+}
+
+/// Runs one measurement: spawns the workers, warms up, samples until a limit is
+/// reached or `interrupted` is set, and collects the results.
+/// Fails without starting if the spec is invalid.
+pub fn run(spec: &RunSpec, outputs: RunOutputs, interrupted: &AtomicBool) -> Result<RunResult, String> {
+    spec.validate()?;
+    let RunOutputs {
+        run,
+        mut observers,
+        metrics_listener,
+        quiet,
+    } = outputs;
+    let num_worker_threads = spec.threads;
+
+    // Per-entry byte counts, so workers do not recompute them every iteration
+    let bytes_touched: Arc<[usize]> = spec
+        .corpus
+        .entries()
+        .iter()
+        .map(|entry| (spec.workload.bytes_touched)(entry.len()))
+        .collect();
+
+    // Shared state: counters, running flag and warmup phase flag.
+    // With a single input every iteration touches the same bytes, so they are not counted.
+    let bytes_per_iteration = match *bytes_touched {
+        [bytes] => Some(bytes),
+        _ => None,
+    };
+    let processed_counters = Arc::new(Counters::new(
+        spec.counter_mode,
+        num_worker_threads,
+        bytes_per_iteration,
+    ));
+    let warmup_counter = Arc::new(AtomicUsize::new(0));
+    let running_flag = Arc::new(AtomicBool::new(true));
+    let warming_flag = Arc::new(AtomicBool::new(spec.warmup.is_some()));
+    let warnings = Warnings::new(quiet);
+
+    let latest_sample = metrics::LatestSample::default();
+    if metrics_listener.is_some() {
+        observers.push(Box::new(MetricsSink::new(Arc::clone(&latest_sample))));
+    }
+
+    // Record launch time (after getting user input, before spawning workers)
+    let launch_time = Instant::now();
+
+    // --- Spawn Threads ---
+    let mut thread_handles: Vec<JoinHandle<Instant>> = Vec::with_capacity(num_worker_threads);
+
+    // Spawn Worker Threads
+    status!(quiet, "Spawning worker threads...");
+    for worker_index in 0..num_worker_threads {
+        // Split an iteration limit evenly, giving the remainder to the first workers
+        let plan = WorkerPlan {
+            index: worker_index,
+            cpu: spec
+                .pinned_cpus
+                .as_ref()
+                .map(|cpus| cpus[worker_index % cpus.len()]),
+            batch_size: spec.batch_size,
+            quota: spec.iterations.map(|iterations| {
+                iterations / num_worker_threads
+                    + usize::from(worker_index < iterations % num_worker_threads)
+            }),
+            selection: spec.selection,
+            seed: spec.seed,
+            bytes_touched: Arc::clone(&bytes_touched),
+            warnings: warnings.clone(),
+        };
+        let processor_corpus_clone = Arc::clone(&spec.corpus);
+        let workload_info = spec.workload;
+        let processor_counters_clone = Arc::clone(&processed_counters);
+        let processor_warmup_clone = Arc::clone(&warmup_counter);
+        let processor_running_clone = Arc::clone(&running_flag);
+        let processor_warming_clone = Arc::clone(&warming_flag);
+
+        let handle = thread::spawn(move || {
+            processor_task(
+                processor_corpus_clone,
+                workload_info,
+                plan,
+                processor_counters_clone,
+                processor_warmup_clone,
+                processor_running_clone,
+                processor_warming_clone,
+            )
+        });
+        thread_handles.push(handle);
+    }
+    status!(quiet, "All worker threads spawned.");
+
+    // --- Warmup ---
+    // Workers run but are not counted; the measurement starts when warmup ends
+    let start_time = match spec.warmup {
+        Some(warmup) => {
+            while !interrupted.load(Ordering::Relaxed) && launch_time.elapsed() < warmup {
+                thread::sleep(SHUTDOWN_CHECK_INTERVAL);
+            }
+            let start_time = Instant::now();
+            warming_flag.store(false, Ordering::Relaxed);
+            status!(quiet, "Warmup complete. Measuring...");
+            start_time
+        }
+        None => launch_time,
+    };
+    let warmup_time = start_time - launch_time;
+    let started_at = SystemTime::now();
+    // --- End Warmup ---
+
+    // Spawn Logger Thread
+    let logger_counters_clone = Arc::clone(&processed_counters);
+    let logger_running_clone = Arc::clone(&running_flag);
+    let logger_warnings_clone = warnings.clone();
+    let log_interval = spec.interval;
+
+    let logger_handle = thread::spawn(move || {
+        logger_task(
+            logger_counters_clone,
+            start_time,
+            observers,
+            logger_running_clone,
+            log_interval,
+            quiet,
+            logger_warnings_clone,
+        )
+    });
+
+    // Spawn Metrics Thread
+    let metrics_handle = metrics_listener.map(|listener| {
+        let source = MetricsSource {
+            counters: Arc::clone(&processed_counters),
+            start_time,
+            latest: latest_sample,
+            run,
+            warnings: warnings.clone(),
+        };
+        let metrics_running_clone = Arc::clone(&running_flag);
+        thread::spawn(move || metrics::metrics_task(listener, source, metrics_running_clone))
+    });
+    // --- End Spawn Threads ---
+
+    // Wait for Ctrl+C or a run limit
+    while !interrupted.load(Ordering::Relaxed) {
+        if let Some(limit) = spec.limit_reached(start_time.elapsed(), processed_counters.total()) {
+            status!(quiet, "\n{} limit reached. Shutting down...", limit);
+            break;
+        }
+        thread::sleep(SHUTDOWN_CHECK_INTERVAL);
+    }
+    running_flag.store(false, Ordering::Relaxed);
+
+    // Wait for all threads to finish
+    status!(quiet, "Waiting for threads to complete...");
+    // The run ends when the last worker stops counting, not when the polling above notices
+    let mut end_time = start_time;
+    for handle in thread_handles {
+        end_time = end_time.max(handle.join().expect("A worker thread panicked"));
+    }
+    let final_count = processed_counters.total();
+    let final_bytes = processed_counters.total_bytes(final_count);
+    let total_time = end_time - start_time;
+    let (interval_rates, mut observers) = logger_handle.join().expect("The logger thread panicked");
+    if let Some(handle) = metrics_handle {
+        handle.join().expect("The metrics thread panicked");
+    }
+
+    let result = RunResult {
+        started_at,
+        total: final_count,
+        bytes: final_bytes,
+        elapsed: total_time,
+        interval_rates,
+        per_worker: processed_counters.per_worker(),
+        warmup_total: warmup_counter.load(Ordering::Relaxed),
+        warmup_elapsed: warmup_time,
+        warnings: Vec::new(),
+    };
+    for observer in observers.iter_mut() {
+        if let Err(e) = observer.on_finish(&result) {
+            warnings.warn(format!("observer failed to finish: {}", e));
+        }
+    }
+    Ok(RunResult {
+        warnings: warnings.messages(),
+        ..result
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io, sync::Mutex};
+
+    use crate::stats::Sample;
+    use crate::Benchmark;
+
+    use super::*;
+
+    /// Counts the samples and finishes it receives.
+    struct Counting(Arc<Mutex<(usize, usize)>>);
+
+    impl Observer for Counting {
+        fn on_sample(&mut self, _sample: &Sample) -> io::Result<()> {
+            self.0.lock().unwrap().0 += 1;
+            Ok(())
+        }
+
+        fn on_finish(&mut self, _result: &RunResult) -> io::Result<()> {
+            self.0.lock().unwrap().1 += 1;
+            Ok(())
+        }
+    }
+
+    /// Fails on its first sample.
+    struct Failing(Arc<Mutex<(usize, usize)>>);
+
+    impl Observer for Failing {
+        fn on_sample(&mut self, _sample: &Sample) -> io::Result<()> {
+            self.0.lock().unwrap().0 += 1;
+            Err(io::Error::other("disk full"))
+        }
+
+        fn on_finish(&mut self, _result: &RunResult) -> io::Result<()> {
+            self.0.lock().unwrap().1 += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn failing_observer_is_detached_and_others_keep_sampling() {
+        let counting = Arc::new(Mutex::new((0, 0)));
+        let failing = Arc::new(Mutex::new((0, 0)));
+        let result = Benchmark::new("hello")
+            .threads(1)
+            .interval(Duration::from_millis(5))
+            .duration(Duration::from_millis(100))
+            .observer(Failing(Arc::clone(&failing)))
+            .observer(Counting(Arc::clone(&counting)))
+            .run()
+            .unwrap();
+
+        assert!(result.interval_rates.len() > 1);
+        assert_eq!(result.warnings.len(), 1);
+        assert!(result.warnings[0].starts_with("detaching an observer"), "{:?}", result.warnings);
+        assert_eq!(*failing.lock().unwrap(), (1, 0));
+        assert_eq!(*counting.lock().unwrap(), (result.interval_rates.len(), 1));
+    }
+
+    #[test]
+    fn iteration_limited_runs_end_when_the_workers_finish() {
+        let before = Instant::now();
+        let result = Benchmark::new("hello")
+            .threads(2)
+            .iterations(1000)
+            .run()
+            .unwrap();
+        let wall_clock = before.elapsed();
+        assert_eq!(result.total, 1000);
+        assert!(result.elapsed <= wall_clock, "{:?} > {:?}", result.elapsed, wall_clock);
+    }
+
+    #[test]
+    fn invalid_specs_fail_without_running() {
+        let short = || Benchmark::new("hello").duration(Duration::from_millis(10));
+        let cases = [
+            ("empty CPU list", short().pin(Vec::new())),
+            ("CPU out of range", short().pin(vec![crate::affinity::MAX_CPU + 1])),
+            ("zero threads", short().threads(0)),
+            ("zero batch size", short().batch_size(0)),
+            ("zero interval", short().interval(Duration::ZERO)),
+        ];
+        for (name, benchmark) in cases {
+            assert!(benchmark.run().is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn quiet_runs_return_warnings_instead_of_printing_them() {
+        // Pinning to a CPU the machine does not have fails, but the run carries on
+        let result = Benchmark::new("hello")
+            .threads(1)
+            .pin(vec![crate::affinity::MAX_CPU])
+            .iterations(10)
+            .run()
+            .unwrap();
+        assert_eq!(result.total, 10);
+        assert_eq!(result.warnings.len(), 1, "{:?}", result.warnings);
+        assert!(result.warnings[0].contains("CPU 1023"), "{:?}", result.warnings);
+    }
+}
@@ -0,0 +1,253 @@
+use crate::json::Value;
+
+/// Throughput at one thread count relative to the single-thread throughput.
+#[derive(Clone, Debug)]
+pub struct ScalingPoint {
+    pub threads: usize,
+    pub rate: f64,
+    /// `rate` divided by the single-thread rate.
+    pub speedup: f64,
+    /// Speedup per thread; 1.0 is perfectly linear scaling.
+    pub efficiency: f64,
+}
+
+/// Amdahl's law, `S(n) = n / (1 + σ(n - 1))`, fitted to the measured speedups.
+#[derive(Clone, Debug)]
+pub struct AmdahlFit {
+    /// Estimated fraction σ of the work that does not parallelize.
+    pub serial_fraction: f64,
+}
+
+impl AmdahlFit {
+    /// The speedup approached as the thread count grows, `1 / σ`.
+    pub fn max_speedup(&self) -> Option<f64> {
+        (self.serial_fraction > 0.0).then(|| 1.0 / self.serial_fraction)
+    }
+}
+
+/// The Universal Scalability Law, `S(n) = n / (1 + σ(n - 1) + κn(n - 1))`,
+/// fitted to the measured speedups.
+#[derive(Clone, Debug)]
+pub struct UslFit {
+    /// Contention coefficient σ: serialization on shared resources.
+    pub contention: f64,
+    /// Coherency coefficient κ: the cost of keeping shared data consistent
+    /// between threads, which makes throughput fall past a peak.
+    pub coherency: f64,
+}
+
+impl UslFit {
+    /// The thread count with the highest predicted throughput, if throughput peaks.
+    pub fn peak_threads(&self) -> Option<f64> {
+        (self.coherency > 0.0 && self.contention < 1.0)
+            .then(|| ((1.0 - self.contention) / self.coherency).sqrt())
+    }
+}
+
+/// Speedup, efficiency and model fits for one set of measurements.
+#[derive(Clone, Debug)]
+pub struct Scaling {
+    pub points: Vec<ScalingPoint>,
+    /// `None` without a measurement above one thread.
+    pub amdahl: Option<AmdahlFit>,
+    /// `None` without measurements at two different thread counts above one.
+    pub usl: Option<UslFit>,
+}
+
+impl Scaling {
+    /// Analyzes `(threads, rate)` measurements against the single-thread measurement.
+    /// Returns `None` if there is no usable single-thread measurement.
+    pub fn analyze(measurements: &[(usize, f64)]) -> Option<Scaling> {
+        let baseline = measurements
+            .iter()
+            .find(|(threads, _)| *threads == 1)
+            .map(|&(_, rate)| rate)
+            .filter(|&rate| rate > 0.0)?;
+        let points: Vec<ScalingPoint> = measurements
+            .iter()
+            .map(|&(threads, rate)| {
+                let speedup = rate / baseline;
+                ScalingPoint {
+                    threads,
+                    rate,
+                    speedup,
+                    efficiency: speedup / threads as f64,
+                }
+            })
+            .collect();
+
+        // Both laws are linear in their coefficients after rewriting them as
+        // n/S(n) - 1 = σ(n - 1) [+ κn(n - 1)], so fit them by least squares
+        // through the origin over the points above one thread, keeping the
+        // coefficients inside the models' domain: 0 <= σ <= 1 and κ >= 0.
+        let terms: Vec<(f64, f64, f64)> = points
+            .iter()
+            .filter(|point| point.threads > 1 && point.speedup > 0.0)
+            .map(|point| {
+                let n = point.threads as f64;
+                let y = n / point.speedup - 1.0;
+                (n - 1.0, n * (n - 1.0), y)
+            })
+            .collect();
+
+        let sum_aa: f64 = terms.iter().map(|(a, _, _)| a * a).sum();
+        let sum_ab: f64 = terms.iter().map(|(a, b, _)| a * b).sum();
+        let sum_bb: f64 = terms.iter().map(|(_, b, _)| b * b).sum();
+        let sum_ay: f64 = terms.iter().map(|(a, _, y)| a * y).sum();
+        let sum_by: f64 = terms.iter().map(|(_, b, y)| b * y).sum();
+
+        // A slowdown gives σ > 1, which means no speedup at all
+        let serial_fraction = (sum_ay / sum_aa).clamp(0.0, 1.0);
+        let amdahl = (sum_aa > 0.0).then_some(AmdahlFit { serial_fraction });
+
+        let determinant = sum_aa * sum_bb - sum_ab * sum_ab;
+        // A near-zero determinant means fewer than two distinct thread counts
+        let usl = (determinant > 1e-9 * sum_aa * sum_bb).then(|| {
+            let contention = (sum_ay * sum_bb - sum_by * sum_ab) / determinant;
+            let coherency = (sum_aa * sum_by - sum_ab * sum_ay) / determinant;
+            if coherency < 0.0 {
+                // No retrograde scaling: the best fit on the κ = 0 edge is Amdahl's
+                UslFit {
+                    contention: serial_fraction,
+                    coherency: 0.0,
+                }
+            } else if contention < 0.0 {
+                UslFit {
+                    contention: 0.0,
+                    coherency: (sum_by / sum_bb).max(0.0),
+                }
+            } else {
+                UslFit {
+                    contention: contention.min(1.0),
+                    coherency,
+                }
+            }
+        });
+
+        Some(Scaling { points, amdahl, usl })
+    }
+
+    /// Renders the analysis as a JSON object.
+    pub fn to_json(&self) -> Value {
+        let points: Vec<Value> = self
+            .points
+            .iter()
+            .map(|point| {
+                Value::object([
+                    ("threads", Value::from(point.threads)),
+                    ("rate", Value::from(point.rate)),
+                    ("speedup", Value::from(point.speedup)),
+                    ("efficiency", Value::from(point.efficiency)),
+                ])
+            })
+            .collect();
+        let amdahl = self.amdahl.as_ref().map_or(Value::Null, |fit| {
+            Value::object([
+                ("serial_fraction", Value::from(fit.serial_fraction)),
+                ("max_speedup", Value::from(fit.max_speedup())),
+            ])
+        });
+        let usl = self.usl.as_ref().map_or(Value::Null, |fit| {
+            Value::object([
+                ("contention", Value::from(fit.contention)),
+                ("coherency", Value::from(fit.coherency)),
+                ("peak_threads", Value::from(fit.peak_threads())),
+            ])
+        });
+        Value::object([
+            ("points", Value::from(points)),
+            ("amdahl", amdahl),
+            ("usl", usl),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const THREADS: [usize; 6] = [1, 2, 4, 8, 16, 32];
+
+    fn usl_curve(contention: f64, coherency: f64) -> Vec<(usize, f64)> {
+        THREADS
+            .iter()
+            .map(|&threads| {
+                let n = threads as f64;
+                (threads, 1000.0 * n / (1.0 + contention * (n - 1.0) + coherency * n * (n - 1.0)))
+            })
+            .collect()
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn fits_recover_an_amdahl_curve() {
+        let scaling = Scaling::analyze(&usl_curve(0.05, 0.0)).unwrap();
+        let amdahl = scaling.amdahl.unwrap();
+        assert_close(amdahl.serial_fraction, 0.05);
+        assert_close(amdahl.max_speedup().unwrap(), 20.0);
+        let usl = scaling.usl.unwrap();
+        assert_close(usl.contention, 0.05);
+        assert_close(usl.coherency, 0.0);
+        assert!(usl.peak_threads().is_none());
+
+        let point = &scaling.points[1];
+        assert_close(point.speedup, 2.0 / 1.05);
+        assert_close(point.efficiency, 1.0 / 1.05);
+    }
+
+    #[test]
+    fn usl_fit_recovers_contention_and_coherency() {
+        let scaling = Scaling::analyze(&usl_curve(0.03, 0.0004)).unwrap();
+        let usl = scaling.usl.unwrap();
+        assert_close(usl.contention, 0.03);
+        assert_close(usl.coherency, 0.0004);
+        assert_close(usl.peak_threads().unwrap(), (0.97f64 / 0.0004).sqrt());
+    }
+
+    #[test]
+    fn perfect_scaling_fits_zero_coefficients() {
+        let scaling = Scaling::analyze(&usl_curve(0.0, 0.0)).unwrap();
+        assert_close(scaling.amdahl.as_ref().unwrap().serial_fraction, 0.0);
+        assert!(scaling.amdahl.unwrap().max_speedup().is_none());
+        assert!(scaling.points.iter().all(|point| (point.efficiency - 1.0).abs() < 1e-12));
+    }
+
+    #[test]
+    fn fits_need_enough_measurements() {
+        assert!(Scaling::analyze(&[(2, 100.0), (4, 200.0)]).is_none());
+        assert!(Scaling::analyze(&[(1, 0.0), (2, 100.0)]).is_none());
+
+        let single = Scaling::analyze(&[(1, 100.0)]).unwrap();
+        assert!(single.amdahl.is_none() && single.usl.is_none());
+
+        let one_count = Scaling::analyze(&[(1, 100.0), (4, 300.0)]).unwrap();
+        assert_close(one_count.amdahl.unwrap().serial_fraction, 1.0 / 9.0);
+        assert!(one_count.usl.is_none());
+    }
+
+    #[test]
+    fn fits_stay_in_the_model_domain_for_a_slowdown() {
+        let slowdown = [(1, 1000.0), (2, 900.0), (4, 800.0), (8, 600.0)];
+        let scaling = Scaling::analyze(&slowdown).unwrap();
+        let amdahl = scaling.amdahl.unwrap();
+        assert_close(amdahl.serial_fraction, 1.0);
+        assert_close(amdahl.max_speedup().unwrap(), 1.0);
+        let usl = scaling.usl.unwrap();
+        assert!((0.0..=1.0).contains(&usl.contention), "{:?}", usl);
+        assert!(usl.coherency >= 0.0, "{:?}", usl);
+    }
+
+    #[test]
+    fn fits_stay_in_the_model_domain_for_superlinear_scaling() {
+        let superlinear = [(1, 1000.0), (2, 2100.0), (4, 4400.0), (8, 9000.0)];
+        let scaling = Scaling::analyze(&superlinear).unwrap();
+        let amdahl = scaling.amdahl.unwrap();
+        assert_close(amdahl.serial_fraction, 0.0);
+        assert!(amdahl.max_speedup().is_none());
+        let usl = scaling.usl.unwrap();
+        assert!(usl.contention >= 0.0 && usl.coherency >= 0.0, "{:?}", usl);
+    }
+}
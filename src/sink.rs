@@ -0,0 +1,394 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Seek, SeekFrom, Write},
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::json::Value;
+use crate::runner::RunResult;
+use crate::stats::{self, Sample};
+
+/// How samples are rendered for the console and the log files.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// The human-readable `Processed: ... | Speed: ...` line.
+    Text,
+    /// One JSON object per line.
+    Json,
+}
+
+impl OutputFormat {
+    pub fn name(self) -> &'static str {
+        match self {
+            OutputFormat::Text => "text",
+            OutputFormat::Json => "json",
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<OutputFormat, String> {
+        match text.trim() {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(format!("unknown format \"{}\" (expected text or json)", text)),
+        }
+    }
+}
+
+/// Identifies the run a sample belongs to in structured output.
+#[derive(Clone, Debug)]
+pub struct RunInfo {
+    pub run_id: String,
+    pub threads: usize,
+    pub mode: String,
+}
+
+/// Problems a run works around instead of failing, such as a worker that could not
+/// be pinned. They are printed to stderr unless the run is quiet, and always kept
+/// for [`RunResult::warnings`].
+#[derive(Clone, Default)]
+pub struct Warnings {
+    messages: Arc<Mutex<Vec<String>>>,
+    quiet: bool,
+}
+
+impl Warnings {
+    pub fn new(quiet: bool) -> Warnings {
+        Warnings {
+            messages: Arc::default(),
+            quiet,
+        }
+    }
+
+    pub fn warn(&self, message: String) {
+        if !self.quiet {
+            eprintln!("Warning: {}", message);
+        }
+        self.messages.lock().expect("Failed to lock warnings").push(message);
+    }
+
+    /// The warnings raised so far, in order.
+    pub fn messages(&self) -> Vec<String> {
+        self.messages.lock().expect("Failed to lock warnings").clone()
+    }
+}
+
+/// Generates a run id from the current time and process id.
+pub fn new_run_id() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    format!("{:x}-{:x}", nanos, std::process::id())
+}
+
+/// Renders samples in the selected output format.
+#[derive(Clone, Debug)]
+pub struct Formatter {
+    pub format: OutputFormat,
+    pub run: RunInfo,
+}
+
+impl Formatter {
+    /// Formats a sample as one line without a newline, tagged with `timestamp` if given.
+    pub fn line(&self, sample: &Sample, timestamp: Option<SystemTime>) -> String {
+        match self.format {
+            OutputFormat::Text => match timestamp {
+                Some(timestamp) => format!("{} {}", format_timestamp(timestamp), format_text(sample)),
+                None => format_text(sample),
+            },
+            OutputFormat::Json => Value::object(self.json_members(sample, timestamp)).to_string(),
+        }
+    }
+
+    /// The longest line `line(sample, None)` can return for this run, or `None` for
+    /// text lines, which are only padded to typical widths. JSON lines are bounded by
+    /// rendering every counter as `u64::MAX` and every rate and time with 23 characters,
+    /// enough for any value below 1e23.
+    pub fn max_line_len(&self) -> Option<usize> {
+        const WIDEST_NUMBER: f64 = 1.234_567_890_123_456_7e22;
+        match self.format {
+            OutputFormat::Text => None,
+            OutputFormat::Json => {
+                let members = self.json_members(&Sample::default(), None).into_iter().map(|(key, value)| {
+                    let widest = match value {
+                        Value::UInt(_) => Value::UInt(u64::MAX),
+                        Value::Number(_) => Value::Number(WIDEST_NUMBER),
+                        value => value,
+                    };
+                    (key, widest)
+                });
+                Some(Value::object(members).to_string().len())
+            }
+        }
+    }
+
+    fn json_members(&self, sample: &Sample, timestamp: Option<SystemTime>) -> Vec<(&'static str, Value)> {
+        let mut members = Vec::new();
+        if let Some(timestamp) = timestamp {
+            members.push(("timestamp", Value::from(format_timestamp(timestamp))));
+        }
+        members.extend([
+            ("run_id", Value::from(self.run.run_id.as_str())),
+            ("mode", Value::from(self.run.mode.as_str())),
+            ("threads", Value::from(self.run.threads)),
+            ("count", Value::from(sample.count)),
+            ("elapsed", Value::from(sample.elapsed.as_secs_f64())),
+            ("interval_rate", Value::from(sample.interval_rate())),
+            ("average_rate", Value::from(sample.average_rate())),
+            ("bytes", Value::from(sample.bytes)),
+            ("interval_byte_rate", Value::from(sample.interval_byte_rate())),
+            ("average_byte_rate", Value::from(sample.average_byte_rate())),
+        ]);
+        members
+    }
+}
+
+/// Receives the periodic samples taken by the logger. Any number of observers
+/// can be attached to a run; each sees every sample, in the order attached.
+pub trait Observer: Send {
+    /// Called with each sample, on the logger thread. After an error the observer
+    /// is detached: it receives no further samples and `on_finish` is not called.
+    fn on_sample(&mut self, sample: &Sample) -> io::Result<()>;
+
+    /// Called once with the results after the run has stopped.
+    fn on_finish(&mut self, _result: &RunResult) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Prints each sample to stdout, the same content as the log files.
+pub struct ConsoleSink {
+    formatter: Formatter,
+}
+
+impl ConsoleSink {
+    pub fn new(formatter: Formatter) -> ConsoleSink {
+        ConsoleSink { formatter }
+    }
+}
+
+impl Observer for ConsoleSink {
+    fn on_sample(&mut self, sample: &Sample) -> io::Result<()> {
+        let line = self.formatter.line(sample, None);
+        match self.formatter.format {
+            OutputFormat::Text => print!("{}", line),
+            OutputFormat::Json => println!("{}", line),
+        }
+        Ok(())
+    }
+}
+
+/// Keeps only the latest sample, rewriting the file in place as one fixed-width record.
+/// The file is always exactly one record long, so a reader never sees a mix of two records.
+pub struct SnapshotSink {
+    file: File,
+    /// The record being written, `width` bytes including the trailing newline.
+    record: Vec<u8>,
+    formatter: Formatter,
+}
+
+impl SnapshotSink {
+    /// Creates the file holding one blank record of `width` bytes.
+    pub fn create(path: &str, width: usize, formatter: Formatter) -> io::Result<SnapshotSink> {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        let mut record = vec![0; width.max(1)];
+        fill_record(&mut record, "");
+        file.write_all(&record)?;
+        file.flush()?;
+        Ok(SnapshotSink {
+            file,
+            record,
+            formatter,
+        })
+    }
+}
+
+impl Observer for SnapshotSink {
+    fn on_sample(&mut self, sample: &Sample) -> io::Result<()> {
+        fill_record(&mut self.record, &self.formatter.line(sample, None));
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.write_all(&self.record)?;
+        self.file.flush()
+    }
+}
+
+/// Fills `record` with `line` padded with spaces, or cut at a character boundary
+/// if it does not fit, and terminated with a newline.
+fn fill_record(record: &mut [u8], line: &str) {
+    let content_width = record.len() - 1;
+    let mut end = line.len().min(content_width);
+    while !line.is_char_boundary(end) {
+        end -= 1;
+    }
+    record[..end].copy_from_slice(&line.as_bytes()[..end]);
+    record[end..content_width].fill(b' ');
+    record[content_width] = b'\n';
+}
+
+/// Appends one timestamped record per sample, keeping the full history of a run.
+pub struct SeriesSink {
+    file: File,
+    formatter: Formatter,
+}
+
+impl SeriesSink {
+    pub fn create(path: &str, formatter: Formatter) -> io::Result<SeriesSink> {
+        let file = OpenOptions::new().append(true).create(true).open(path)?;
+        Ok(SeriesSink { file, formatter })
+    }
+}
+
+impl Observer for SeriesSink {
+    fn on_sample(&mut self, sample: &Sample) -> io::Result<()> {
+        let record = format!("{}\n", self.formatter.line(sample, Some(SystemTime::now())));
+        self.file.write_all(record.as_bytes())?;
+        self.file.flush()
+    }
+}
+
+/// Writes samples as CSV rows under a header with a stable column order.
+pub struct CsvSink {
+    file: File,
+}
+
+impl CsvSink {
+    /// Creates the file and writes the header, with one total column per worker
+    /// when `per_worker_columns` is non-zero.
+    pub fn create(path: &str, per_worker_columns: usize) -> io::Result<CsvSink> {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        let mut header =
+            String::from("timestamp,elapsed_s,total,interval_ops,interval_rate,avg_rate,total_bytes,interval_byte_rate,avg_byte_rate");
+        for worker in 0..per_worker_columns {
+            header.push_str(&format!(",thread_{}_total", worker));
+        }
+        header.push('\n');
+        file.write_all(header.as_bytes())?;
+        file.flush()?;
+        Ok(CsvSink { file })
+    }
+}
+
+impl Observer for CsvSink {
+    fn on_sample(&mut self, sample: &Sample) -> io::Result<()> {
+        let mut row = format!(
+            "{},{:.6},{},{},{:.2},{:.2},{},{:.2},{:.2}",
+            format_timestamp(SystemTime::now()),
+            sample.elapsed.as_secs_f64(),
+            sample.count,
+            sample.interval_count,
+            sample.interval_rate(),
+            sample.average_rate(),
+            sample.bytes,
+            sample.interval_byte_rate(),
+            sample.average_byte_rate()
+        );
+        for total in sample.per_worker.iter().flatten() {
+            row.push_str(&format!(",{}", total));
+        }
+        row.push('\n');
+        self.file.write_all(row.as_bytes())?;
+        self.file.flush()
+    }
+}
+
+/// The human-readable statistics line shared by the console and the log files.
+fn format_text(sample: &Sample) -> String {
+    format!(
+        "Processed: {:<15} | Elapsed: {:.2}s | Interval: {:.2}/s | Speed: {:.2}/s | Bytes: {:.0}/s interval, {:.0}/s avg ({:.3} GiB/s)",
+        sample.count,
+        sample.elapsed.as_secs_f64(),
+        sample.interval_rate(),
+        sample.average_rate(),
+        sample.interval_byte_rate(),
+        sample.average_byte_rate(),
+        stats::gib_per_second(sample.average_byte_rate())
+    )
+}
+
+/// Formats a time as an RFC 3339 UTC timestamp with millisecond precision.
+pub fn format_timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = since_epoch.as_secs();
+    let (days, second_of_day) = (seconds / 86_400, seconds % 86_400);
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        second_of_day / 3600,
+        second_of_day % 3600 / 60,
+        second_of_day % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(line: &str, width: usize) -> String {
+        let mut record = vec![0; width];
+        fill_record(&mut record, line);
+        String::from_utf8(record).unwrap()
+    }
+
+    #[test]
+    fn records_are_always_exactly_width_bytes() {
+        assert_eq!(record("abc", 6), "abc  \n");
+        assert_eq!(record("abcde", 6), "abcde\n");
+        assert_eq!(record("abcdefgh", 6), "abcde\n");
+        assert_eq!(record("", 1), "\n");
+    }
+
+    #[test]
+    fn json_lines_fit_their_maximum_length() {
+        let formatter = Formatter {
+            format: OutputFormat::Json,
+            run: RunInfo {
+                run_id: new_run_id(),
+                threads: 64,
+                mode: "deep-clone".to_string(),
+            },
+        };
+        let max_len = formatter.max_line_len().unwrap();
+        let sample = Sample {
+            count: usize::MAX,
+            elapsed: std::time::Duration::from_nanos(1_234_567_891),
+            interval_count: usize::MAX / 3,
+            interval_elapsed: std::time::Duration::from_nanos(333_333_337),
+            bytes: usize::MAX,
+            interval_bytes: usize::MAX / 7,
+            per_worker: None,
+        };
+        assert!(formatter.line(&sample, None).len() <= max_len);
+        assert!(formatter.line(&Sample::default(), None).len() <= max_len);
+    }
+
+    #[test]
+    fn long_lines_are_cut_at_a_character_boundary() {
+        // "é" is two bytes and would straddle the cut, so it is padded instead
+        assert_eq!(record("abcdé", 6), "abcd \n");
+        assert_eq!(record("日本", 5), "日 \n");
+    }
+}
@@ -0,0 +1,167 @@
+use std::time::Duration;
+
+use crate::counter::Counters;
+
+/// One periodic measurement taken by the logger.
+#[derive(Clone, Debug, Default)]
+pub struct Sample {
+    /// Total iterations since the measurement started.
+    pub count: usize,
+    /// Time since the measurement started.
+    pub elapsed: Duration,
+    /// Iterations since the previous sample.
+    pub interval_count: usize,
+    /// Time since the previous sample.
+    pub interval_elapsed: Duration,
+    /// Total bytes touched since the measurement started.
+    pub bytes: usize,
+    /// Bytes touched since the previous sample.
+    pub interval_bytes: usize,
+    /// Total iterations of each worker, when counted per worker.
+    pub per_worker: Option<Vec<usize>>,
+}
+
+impl Sample {
+    /// Iterations per second since the previous sample.
+    pub fn interval_rate(&self) -> f64 {
+        rate(self.interval_count, self.interval_elapsed)
+    }
+
+    /// Iterations per second since the measurement started.
+    pub fn average_rate(&self) -> f64 {
+        rate(self.count, self.elapsed)
+    }
+
+    /// Bytes per second since the previous sample.
+    pub fn interval_byte_rate(&self) -> f64 {
+        rate(self.interval_bytes, self.interval_elapsed)
+    }
+
+    /// Bytes per second since the measurement started.
+    pub fn average_byte_rate(&self) -> f64 {
+        rate(self.bytes, self.elapsed)
+    }
+}
+
+/// Turns cumulative readings into samples by remembering the previous reading.
+#[derive(Default)]
+pub struct Sampler {
+    last_count: usize,
+    last_bytes: usize,
+    last_elapsed: Duration,
+}
+
+impl Sampler {
+    pub fn sample(&mut self, counters: &Counters, elapsed: Duration) -> Sample {
+        // Derive the total from the per-worker reading so both agree
+        let per_worker = counters.per_worker();
+        let count = match &per_worker {
+            Some(per_worker) => per_worker.iter().sum(),
+            None => counters.total(),
+        };
+        let bytes = counters.total_bytes(count);
+        let sample = Sample {
+            count,
+            elapsed,
+            interval_count: count.saturating_sub(self.last_count),
+            interval_elapsed: elapsed.saturating_sub(self.last_elapsed),
+            bytes,
+            interval_bytes: bytes.saturating_sub(self.last_bytes),
+            per_worker,
+        };
+        self.last_count = count;
+        self.last_bytes = bytes;
+        self.last_elapsed = elapsed;
+        sample
+    }
+}
+
+/// Summary statistics over the interval rates of a run.
+#[derive(Clone, Debug)]
+pub struct RateSummary {
+    pub samples: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub median: f64,
+    /// Sample standard deviation (zero for a single sample).
+    pub stddev: f64,
+}
+
+impl RateSummary {
+    /// Summarizes a set of rates, or returns `None` if there are none.
+    pub fn from_rates(rates: &[f64]) -> Option<RateSummary> {
+        if rates.is_empty() {
+            return None;
+        }
+        let n = rates.len() as f64;
+        let mean = rates.iter().sum::<f64>() / n;
+        let variance = if rates.len() > 1 {
+            rates.iter().map(|rate| (rate - mean).powi(2)).sum::<f64>() / (n - 1.0)
+        } else {
+            0.0
+        };
+        let mut sorted = rates.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let middle = sorted.len() / 2;
+        let median = if sorted.len().is_multiple_of(2) {
+            (sorted[middle - 1] + sorted[middle]) / 2.0
+        } else {
+            sorted[middle]
+        };
+        Some(RateSummary {
+            samples: rates.len(),
+            min: rates.iter().copied().fold(f64::INFINITY, f64::min),
+            max: rates.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            mean,
+            median,
+            stddev: variance.sqrt(),
+        })
+    }
+}
+
+/// Iterations per second, or zero if no time has passed.
+pub fn rate(count: usize, elapsed: Duration) -> f64 {
+    let seconds = elapsed.as_secs_f64();
+    if seconds > 0.0 {
+        count as f64 / seconds
+    } else {
+        0.0
+    }
+}
+
+/// Converts a rate in bytes per second to GiB per second.
+pub fn gib_per_second(byte_rate: f64) -> f64 {
+    byte_rate / (1u64 << 30) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summarizes_rates() {
+        // Rates, then the expected min, max, mean, median and stddev
+        let cases: [(&[f64], [f64; 5]); 5] = [
+            (&[5.0], [5.0, 5.0, 5.0, 5.0, 0.0]),
+            (&[4.0, 1.0, 3.0, 2.0], [1.0, 4.0, 2.5, 2.5, (5.0f64 / 3.0).sqrt()]),
+            (&[9.0, 1.0, 5.0], [1.0, 9.0, 5.0, 5.0, 4.0]),
+            (&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0], [2.0, 9.0, 5.0, 4.5, (32.0f64 / 7.0).sqrt()]),
+            (&[3.0, 3.0], [3.0, 3.0, 3.0, 3.0, 0.0]),
+        ];
+        for (rates, [min, max, mean, median, stddev]) in cases {
+            let summary = RateSummary::from_rates(rates).unwrap();
+            assert_eq!(summary.samples, rates.len(), "{:?}", rates);
+            assert_eq!((summary.min, summary.max, summary.mean, summary.median), (min, max, mean, median), "{:?}", rates);
+            assert!((summary.stddev - stddev).abs() < 1e-12, "{:?}: {}", rates, summary.stddev);
+        }
+        assert!(RateSummary::from_rates(&[]).is_none());
+    }
+
+    #[test]
+    fn rates_are_zero_without_elapsed_time() {
+        assert_eq!(rate(500, Duration::from_millis(250)), 2000.0);
+        assert_eq!(rate(500, Duration::ZERO), 0.0);
+        assert_eq!(gib_per_second((3u64 << 30) as f64), 3.0);
+    }
+}
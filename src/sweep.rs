@@ -0,0 +1,285 @@
+use std::{
+    fmt::Write as _,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime},
+};
+
+use crate::corpus::Corpus;
+use crate::generate::{self, Charset};
+use crate::json::Value;
+use crate::report;
+use crate::runner::{self, RunOutputs, RunResult, RunSpec};
+use crate::scaling::Scaling;
+use crate::sink::{self, RunInfo};
+use crate::stats::{self, RateSummary};
+
+/// How long each cell is measured when neither a duration nor an iteration limit is given.
+pub const DEFAULT_CELL_DURATION: Duration = Duration::from_secs(1);
+/// How long each cell warms up when no warmup is given.
+pub const DEFAULT_CELL_WARMUP: Duration = Duration::from_millis(250);
+
+/// The thread counts and input sizes to run, and the settings shared by every cell.
+pub struct SweepPlan {
+    /// Settings for each cell; its input and thread count are replaced per cell.
+    pub base: RunSpec,
+    pub threads: Vec<usize>,
+    pub sizes: Vec<usize>,
+    /// Characters and seed of the generated inputs.
+    pub charset: Charset,
+    pub seed: u64,
+}
+
+/// The result of one thread count and input size combination.
+pub struct Cell {
+    pub threads: usize,
+    pub input_bytes: usize,
+    pub result: RunResult,
+}
+
+impl Cell {
+    pub fn average_rate(&self) -> f64 {
+        stats::rate(self.result.total, self.result.elapsed)
+    }
+}
+
+/// Powers of two up to `max`, plus `max` itself.
+pub fn default_threads(max: usize) -> Vec<usize> {
+    let mut threads: Vec<usize> = std::iter::successors(Some(1), |n: &usize| n.checked_mul(2))
+        .take_while(|&n| n < max)
+        .collect();
+    threads.push(max.max(1));
+    threads
+}
+
+/// Runs every cell of the plan, rows of sizes by columns of threads, until done or interrupted.
+/// A cell cut short by an interruption is discarded. Fails if a cell's spec is invalid.
+pub fn run(plan: &SweepPlan, run_id: &str, interrupted: &AtomicBool, quiet: bool) -> Result<Vec<Cell>, String> {
+    let total_cells = plan.sizes.len() * plan.threads.len();
+    let mut cells = Vec::with_capacity(total_cells);
+    for &size in &plan.sizes {
+        let corpus = Arc::new(Corpus::single(generate::generate(size, plan.charset, plan.seed)));
+        for &threads in &plan.threads {
+            let spec = RunSpec {
+                corpus: Arc::clone(&corpus),
+                threads,
+                ..plan.base.clone()
+            };
+            let outputs = RunOutputs {
+                run: RunInfo {
+                    run_id: run_id.to_string(),
+                    threads,
+                    mode: plan.base.workload.name.to_string(),
+                },
+                observers: Vec::new(),
+                metrics_listener: None,
+                quiet: true,
+            };
+            // Cells run quiet to keep their progress messages off the console
+            let result = runner::run(&spec, outputs, interrupted)?;
+            if !quiet {
+                for warning in &result.warnings {
+                    eprintln!("Warning: {}", warning);
+                }
+            }
+            if interrupted.load(Ordering::Relaxed) {
+                return Ok(cells);
+            }
+            let cell = Cell {
+                threads,
+                input_bytes: size,
+                result,
+            };
+            status!(
+                quiet,
+                "[{}/{}] {:>9} x {:>3} threads: {} repetitions/s, {:.3} GiB/s",
+                cells.len() + 1,
+                total_cells,
+                format_bytes(size),
+                threads,
+                format_si(cell.average_rate()),
+                stats::gib_per_second(stats::rate(cell.result.bytes, cell.result.elapsed))
+            );
+            cells.push(cell);
+        }
+    }
+    Ok(cells)
+}
+
+/// Renders the average rates as a table of sizes by thread counts.
+/// Cells that did not run are shown as `-`.
+pub fn table(plan: &SweepPlan, cells: &[Cell]) -> String {
+    let mut text = String::new();
+    let _ = write!(text, "{:>10}", "size");
+    for threads in &plan.threads {
+        let _ = write!(text, " {:>9}", format!("{}T", threads));
+    }
+    text.push('\n');
+    for &size in &plan.sizes {
+        let _ = write!(text, "{:>10}", format_bytes(size));
+        for &threads in &plan.threads {
+            let rate = cells
+                .iter()
+                .find(|cell| cell.threads == threads && cell.input_bytes == size)
+                .map_or_else(|| "-".to_string(), |cell| format_si(cell.average_rate()));
+            let _ = write!(text, " {:>9}", rate);
+        }
+        text.push('\n');
+    }
+    text
+}
+
+/// Analyzes how each input size scales with the thread count, in the order of `plan.sizes`.
+/// Sizes without a single-thread cell have no analysis.
+pub fn scaling(plan: &SweepPlan, cells: &[Cell]) -> Vec<(usize, Option<Scaling>)> {
+    plan.sizes
+        .iter()
+        .map(|&size| {
+            let measurements: Vec<(usize, f64)> = cells
+                .iter()
+                .filter(|cell| cell.input_bytes == size)
+                .map(|cell| (cell.threads, cell.average_rate()))
+                .collect();
+            (size, Scaling::analyze(&measurements))
+        })
+        .collect()
+}
+
+/// Renders speedup and efficiency per cell, then the Amdahl and USL fits per input size.
+pub fn scaling_table(plan: &SweepPlan, cells: &[Cell]) -> String {
+    let analyses = scaling(plan, cells);
+    let mut text = String::new();
+    let _ = write!(text, "{:>10}", "size");
+    for threads in &plan.threads {
+        let _ = write!(text, " {:>12}", format!("{}T", threads));
+    }
+    text.push('\n');
+    for (size, analysis) in &analyses {
+        let _ = write!(text, "{:>10}", format_bytes(*size));
+        for &threads in &plan.threads {
+            let point = analysis
+                .as_ref()
+                .and_then(|analysis| analysis.points.iter().find(|point| point.threads == threads));
+            let entry = point.map_or_else(
+                || "-".to_string(),
+                |point| format!("{:.2}x {:.0}%", point.speedup, point.efficiency * 100.0),
+            );
+            let _ = write!(text, " {:>12}", entry);
+        }
+        text.push('\n');
+    }
+
+    let _ = writeln!(
+        text,
+        "\n{:>10} {:>14} {:>12} {:>14} {:>14} {:>12}",
+        "size", "Amdahl serial", "max speedup", "USL contention", "USL coherency", "peak threads"
+    );
+    for (size, analysis) in &analyses {
+        let amdahl = analysis.as_ref().and_then(|analysis| analysis.amdahl.as_ref());
+        let usl = analysis.as_ref().and_then(|analysis| analysis.usl.as_ref());
+        let _ = writeln!(
+            text,
+            "{:>10} {:>14} {:>12} {:>14} {:>14} {:>12}",
+            format_bytes(*size),
+            format_optional(amdahl.map(|fit| fit.serial_fraction), |value| format!("{:.4}", value)),
+            format_optional(amdahl.and_then(|fit| fit.max_speedup()), |value| format!("{:.2}x", value)),
+            format_optional(usl.map(|fit| fit.contention), |value| format!("{:.4}", value)),
+            format_optional(usl.map(|fit| fit.coherency), |value| format!("{:.6}", value)),
+            format_optional(usl.and_then(|fit| fit.peak_threads()), |value| format!("{:.1}", value)),
+        );
+    }
+    text
+}
+
+fn format_optional(value: Option<f64>, format: impl Fn(f64) -> String) -> String {
+    value.map_or_else(|| "-".to_string(), format)
+}
+
+/// Builds the structured sweep results: plan, environment, one entry per cell
+/// and the scaling analysis of each input size.
+pub fn report(plan: &SweepPlan, run_id: &str, started_at: SystemTime, cells: &[Cell]) -> Value {
+    let base = &plan.base;
+    let configuration = Value::object([
+        ("mode", Value::from(base.workload.name)),
+        ("threads", Value::from(plan.threads.clone())),
+        ("sizes", Value::from(plan.sizes.clone())),
+        ("charset", Value::from(plan.charset.name())),
+        ("seed", Value::from(plan.seed)),
+        ("pinned_cpus", Value::from(base.pinned_cpus.clone())),
+        ("counter", Value::from(base.counter_mode.name())),
+        ("batch", Value::from(base.batch_size)),
+        ("duration_s", Value::from(base.duration.map(|d| d.as_secs_f64()))),
+        ("iterations", Value::from(base.iterations)),
+        ("warmup_s", Value::from(base.warmup.map(|d| d.as_secs_f64()))),
+        ("interval_s", Value::from(base.interval.as_secs_f64())),
+    ]);
+    let results: Vec<Value> = cells
+        .iter()
+        .map(|cell| {
+            let median = RateSummary::from_rates(&cell.result.interval_rates).map(|summary| summary.median);
+            Value::object([
+                ("threads", Value::from(cell.threads)),
+                ("input_bytes", Value::from(cell.input_bytes)),
+                ("total", Value::from(cell.result.total)),
+                ("elapsed_s", Value::from(cell.result.elapsed.as_secs_f64())),
+                ("bytes", Value::from(cell.result.bytes)),
+                ("average_rate", Value::from(cell.average_rate())),
+                ("average_byte_rate", Value::from(stats::rate(cell.result.bytes, cell.result.elapsed))),
+                ("median_interval_rate", Value::from(median)),
+            ])
+        })
+        .collect();
+
+    let scaling: Vec<Value> = scaling(plan, cells)
+        .into_iter()
+        .map(|(size, analysis)| {
+            let mut members = vec![("input_bytes".to_string(), Value::from(size))];
+            if let Some(Value::Object(analysis)) = analysis.map(|analysis| analysis.to_json()) {
+                members.extend(analysis);
+            }
+            Value::object(members)
+        })
+        .collect();
+
+    Value::object([
+        ("run_id", Value::from(run_id)),
+        ("started_at", Value::from(sink::format_timestamp(started_at))),
+        ("sweep", configuration),
+        ("environment", report::environment()),
+        ("cells", Value::from(results)),
+        ("scaling", Value::from(scaling)),
+    ])
+}
+
+/// Formats a byte count with the largest binary unit that divides it, e.g. `64KiB`.
+pub fn format_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes;
+    let mut unit = 0;
+    while unit + 1 < UNITS.len() && value >= 1024 && value.is_multiple_of(1024) {
+        value /= 1024;
+        unit += 1;
+    }
+    format!("{}{}", value, UNITS[unit])
+}
+
+/// Formats a rate with an SI suffix and three significant digits, e.g. `12.3M`.
+fn format_si(value: f64) -> String {
+    const SUFFIXES: [&str; 5] = ["", "k", "M", "G", "T"];
+    let mut value = value;
+    let mut suffix = 0;
+    while value >= 1000.0 && suffix + 1 < SUFFIXES.len() {
+        value /= 1000.0;
+        suffix += 1;
+    }
+    let precision = if value >= 100.0 {
+        0
+    } else if value >= 10.0 {
+        1
+    } else {
+        2
+    };
+    format!("{:.*}{}", precision, value, SUFFIXES[suffix])
+}
@@ -0,0 +1,170 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    fmt::Write as _,
+    hash::{Hash, Hasher},
+    hint::black_box,
+    sync::Arc,
+};
+
+/// A unit of work repeated by every worker thread.
+/// Each thread gets its own instance, so implementations may keep scratch state.
+pub trait Workload: Send {
+    /// Called once per thread before the first iteration.
+    fn setup(&mut self, _input: &Arc<String>) {}
+
+    /// Performs a single iteration on the input.
+    fn iterate(&mut self, input: &Arc<String>);
+
+    /// Called once per thread after the last iteration.
+    fn teardown(&mut self) {}
+}
+
+/// A named workload that can be selected at startup.
+pub struct WorkloadInfo {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub create: fn() -> Box<dyn Workload>,
+    /// Input bytes one iteration reads, given the input length; used for bytes/s.
+    pub bytes_touched: fn(usize) -> usize,
+}
+
+/// All available workloads, in the order they are listed to the user.
+pub const WORKLOADS: &[WorkloadInfo] = &[
+    WorkloadInfo {
+        name: "deep-clone",
+        description: "allocate, copy and free a full copy of the string",
+        create: || Box::new(DeepClone),
+        bytes_touched: |len| len,
+    },
+    WorkloadInfo {
+        name: "arc-clone",
+        description: "bump and drop the shared Arc reference count only",
+        create: || Box::new(ArcClone),
+        // Only the reference count is touched, never the string contents
+        bytes_touched: |_| 0,
+    },
+    WorkloadInfo {
+        name: "hash",
+        description: "hash the string with the standard library SipHash",
+        create: || Box::new(HashString),
+        bytes_touched: |len| len,
+    },
+    WorkloadInfo {
+        name: "encode",
+        description: "encode the string as UTF-16 into a reused buffer",
+        create: || Box::new(EncodeUtf16::default()),
+        bytes_touched: |len| len,
+    },
+    WorkloadInfo {
+        name: "format",
+        description: "format the string into a reused buffer with write!",
+        create: || Box::new(FormatString::default()),
+        bytes_touched: |len| len,
+    },
+    WorkloadInfo {
+        name: "copy",
+        description: "memcpy the string bytes into a preallocated buffer",
+        create: || Box::new(CopyBytes::default()),
+        bytes_touched: |len| len,
+    },
+];
+
+pub const DEFAULT_WORKLOAD: &str = "deep-clone";
+
+/// Looks up a workload by name.
+pub fn find(name: &str) -> Option<&'static WorkloadInfo> {
+    WORKLOADS.iter().find(|workload| workload.name == name)
+}
+
+struct ArcClone;
+
+impl Workload for ArcClone {
+    fn iterate(&mut self, input: &Arc<String>) {
+        black_box(Arc::clone(black_box(input)));
+    }
+}
+
+struct DeepClone;
+
+impl Workload for DeepClone {
+    fn iterate(&mut self, input: &Arc<String>) {
+        let source: &String = input;
+        black_box(black_box(source).clone());
+    }
+}
+
+struct HashString;
+
+impl Workload for HashString {
+    fn iterate(&mut self, input: &Arc<String>) {
+        let mut hasher = DefaultHasher::new();
+        black_box(input.as_str()).hash(&mut hasher);
+        black_box(hasher.finish());
+    }
+}
+
+#[derive(Default)]
+struct EncodeUtf16 {
+    buffer: Vec<u16>,
+}
+
+impl Workload for EncodeUtf16 {
+    fn setup(&mut self, input: &Arc<String>) {
+        self.buffer = Vec::with_capacity(input.len());
+    }
+
+    fn iterate(&mut self, input: &Arc<String>) {
+        self.buffer.clear();
+        self.buffer.extend(black_box(input.as_str()).encode_utf16());
+        black_box(&self.buffer);
+    }
+
+    fn teardown(&mut self) {
+        self.buffer = Vec::new();
+    }
+}
+
+#[derive(Default)]
+struct FormatString {
+    buffer: String,
+}
+
+impl Workload for FormatString {
+    fn setup(&mut self, input: &Arc<String>) {
+        self.buffer = String::with_capacity(input.len());
+    }
+
+    fn iterate(&mut self, input: &Arc<String>) {
+        self.buffer.clear();
+        let _ = write!(self.buffer, "{}", black_box(input.as_str()));
+        black_box(&self.buffer);
+    }
+
+    fn teardown(&mut self) {
+        self.buffer = String::new();
+    }
+}
+
+#[derive(Default)]
+struct CopyBytes {
+    buffer: Vec<u8>,
+}
+
+impl Workload for CopyBytes {
+    fn setup(&mut self, input: &Arc<String>) {
+        self.buffer = vec![0; input.len()];
+    }
+
+    fn iterate(&mut self, input: &Arc<String>) {
+        let bytes = black_box(input.as_bytes());
+        if self.buffer.len() != bytes.len() {
+            self.buffer.resize(bytes.len(), 0);
+        }
+        self.buffer.copy_from_slice(bytes);
+        black_box(&self.buffer);
+    }
+
+    fn teardown(&mut self) {
+        self.buffer = Vec::new();
+    }
+}